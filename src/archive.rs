@@ -0,0 +1,187 @@
+use super::catalogue::{self, fold};
+use super::cli::MirrorSelection;
+use super::filters::Filters;
+use super::info::{self, Info};
+use super::{problem, storage};
+use colored::Colorize;
+use reqwest::blocking::Client;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+const INFO_FILE: &str = "info.json";
+const STATEMENTS: [(&str, &str); 2] = [("ro", "statement-ro.md"), ("en", "statement-en.md")];
+
+/// Where mirrored problems live, one directory per problem id.
+fn dir() -> PathBuf {
+    storage::data_dir().join("archive")
+}
+
+/// Attachment names never contain a path, don't let one escape the archive.
+pub(crate) fn is_plain_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+}
+
+/// An attachment of a mirrored problem, e.g. a statement or an image it shows.
+pub(crate) fn attachment(id: &str, name: &str) -> Option<Vec<u8>> {
+    if id.parse::<u64>().is_err() || !is_plain_name(name) {
+        return None;
+    }
+    fs::read(dir().join(id).join(name)).ok()
+}
+
+pub(crate) fn info(id: &str) -> Option<Info> {
+    id.parse::<u64>().ok()?;
+    let content = fs::read_to_string(dir().join(id).join(INFO_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Saves the public attachments and the metadata of a problem, returns how many files were saved.
+fn mirror_problem(client: &Client, id: &str) -> Result<usize, String> {
+    let target = dir().join(id);
+    fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+
+    let info = info::fetch(client, id).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
+    fs::write(target.join(INFO_FILE), content).map_err(|e| e.to_string())?;
+
+    let list = problem::attachments(client, id).map_err(|e| e.to_string())?;
+    let mut saved = 1;
+    for file in list.iter().filter(|a| !a.private && is_plain_name(&a.name)) {
+        let data = problem::attachment(client, id, &file.name).map_err(|e| e.to_string())?;
+        fs::write(target.join(&file.name), data).map_err(|e| e.to_string())?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+pub fn mirror(selection: &MirrorSelection) {
+    if selection.ids.is_empty()
+        && selection.list.is_none()
+        && selection.tags.is_empty()
+        && !selection.all
+    {
+        println!(
+            "{}",
+            "Choose the problems to mirror: give their ids, a --list, some --tag or --all".yellow()
+        );
+        return;
+    }
+
+    let client = Client::new();
+    let mut ids: Vec<String> = selection.ids.iter().map(u64::to_string).collect();
+    if selection.all || selection.list.is_some() || !selection.tags.is_empty() {
+        let mut filters = Filters {
+            list: selection.list,
+            ..Default::default()
+        };
+        let found = filters
+            .resolve(&client, &selection.tags, None)
+            .and_then(|()| catalogue::fetch_all(&client, &filters).map_err(|e| e.to_string()));
+        match found {
+            Ok(problems) => ids.extend(problems.iter().map(|p| p.id.to_string())),
+            Err(e) => {
+                println!("{} {}", "Couldn't list the problems:".red(), e);
+                return;
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(id.clone()));
+
+    let mut failed = 0;
+    for (i, id) in ids.iter().enumerate() {
+        print!("[{}/{}] #{} ", i + 1, ids.len(), id);
+        match mirror_problem(&client, id) {
+            Ok(files) => println!("{} files", files),
+            Err(e) => {
+                failed += 1;
+                println!("{} {}", "failed:".red(), e);
+            }
+        }
+    }
+    let mirrored = format!(
+        "Mirrored {} problems to {}",
+        ids.len() - failed,
+        dir().display()
+    );
+    if failed == 0 {
+        println!("{}", mirrored.green());
+    } else {
+        println!("{}", format!("{}, {} failed", mirrored, failed).yellow());
+    }
+}
+
+/// The lines of `text` containing `pattern`, ignoring case and diacritics, numbered from 1.
+fn find(text: &str, pattern: &str) -> Vec<(usize, String)> {
+    let pattern = fold(pattern);
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| fold(line).contains(&pattern))
+        .map(|(i, line)| (i + 1, line.trim().to_string()))
+        .collect()
+}
+
+/// Searches the statements of every mirrored problem.
+pub fn grep(pattern: &str) {
+    let Ok(entries) = fs::read_dir(dir()) else {
+        println!(
+            "{}",
+            "The archive is empty, download problems with `mirror` first".yellow()
+        );
+        return;
+    };
+    let mut ids: Vec<u64> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    ids.sort();
+
+    let mut total = 0;
+    for id in ids {
+        let id = id.to_string();
+        let name = info(&id).map_or(String::new(), |info| format!("{} ", info.details.name));
+        for (language, file) in STATEMENTS {
+            let Some(text) = attachment(&id, file).and_then(|data| String::from_utf8(data).ok())
+            else {
+                continue;
+            };
+            for (number, line) in find(&text, pattern) {
+                total += 1;
+                println!(
+                    "{} {}{}: {}",
+                    format!("#{}", id).bright_black(),
+                    name.bold(),
+                    format!("[{}:{}]", language, number).cyan(),
+                    line
+                );
+            }
+        }
+    }
+    if total == 0 {
+        println!("{}", "No statement mentions that".yellow());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_lines_without_diacritics() {
+        let text = "# Cerință\n\nSă se afle suma.\nAfișați SUMA minimă.\n";
+        assert_eq!(
+            find(text, "suma"),
+            vec![
+                (3, "Să se afle suma.".to_string()),
+                (4, "Afișați SUMA minimă.".to_string())
+            ]
+        );
+        assert_eq!(find(text, "cerinta"), vec![(1, "# Cerință".to_string())]);
+    }
+
+    #[test]
+    fn keeps_attachment_names_inside_the_archive() {
+        assert_eq!(attachment("1", "../../secret"), None);
+        assert_eq!(attachment("1", ".hidden"), None);
+    }
+}
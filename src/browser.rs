@@ -1,6 +1,11 @@
-use super::credential_manager;
+use super::cli::SearchFilters;
+use super::filters::{self, Filters};
+use super::{
+    archive, catalogue, credential_manager, history, images, info, markdown, pager, problem,
+};
 use base64::Engine;
 use base64::engine::general_purpose;
+use colored::Colorize;
 use crossterm::style::Print;
 use crossterm::{
     cursor,
@@ -11,36 +16,213 @@ use crossterm::{
 };
 use reqwest::blocking::Client;
 use scraper::{Html, Selector};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::{Stdout, Write, stdout};
 use std::time::{Duration, Instant};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-#[derive(Deserialize, Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-struct AttachmentData {
-    data: String,
+pub struct ProblemSummary {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub source_credits: String,
+    /// The logged in user's best score, negative when they never submitted.
+    #[serde(default)]
+    pub max_score: Option<f64>,
+    #[serde(default = "default_score_scale")]
+    pub score_scale: f64,
+    /// How many users solved it.
+    #[serde(default)]
+    pub solved_by: Option<u64>,
+    #[serde(default)]
+    pub tags: Vec<problem::Tag>,
 }
 
-#[derive(Deserialize, Debug)]
-struct ApiResponse {
-    data: AttachmentData,
+fn default_score_scale() -> f64 {
+    100.0
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub struct ProblemSummary {
-    pub id: u64,
-    pub name: String,
+impl ProblemSummary {
+    /// The olympiad or contest it's from, falling back to the source credits.
+    fn source(&self) -> String {
+        let contests: Vec<&str> = self
+            .tags
+            .iter()
+            .filter(|tag| tag.kind == "contest")
+            .map(|tag| tag.name.as_str())
+            .collect();
+        if contests.is_empty() {
+            self.source_credits.clone()
+        } else {
+            contests.join(", ")
+        }
+    }
+
+    fn solves(&self) -> String {
+        self.solved_by
+            .map_or(String::new(), |count| format!("{} solves", count))
+    }
+
+    fn tags(&self) -> String {
+        let tags: Vec<&str> = self
+            .tags
+            .iter()
+            .filter(|tag| tag.kind != "contest")
+            .map(|tag| tag.name.as_str())
+            .collect();
+        tags.join(", ")
+    }
+
+    /// The best score with a dot: green when solved, yellow for partial scores, red for zero.
+    fn score(&self, width: usize) -> String {
+        match self.max_score.filter(|score| *score >= 0.0) {
+            None => fit(&format!("{:>5}", "·"), width)
+                .bright_black()
+                .to_string(),
+            Some(score) => {
+                let cell = fit(&format!("{:>3} ●", score.floor()), width);
+                if score >= self.score_scale {
+                    cell.green().to_string()
+                } else if score > 0.0 {
+                    cell.yellow().to_string()
+                } else {
+                    cell.red().to_string()
+                }
+            }
+        }
+    }
 }
 
-fn fetch_summaries(
+/// Cuts `text` to `width` columns, marking the cut with `…`, and pads it to exactly `width`.
+fn fit(text: &str, width: usize) -> String {
+    if width == 0 {
+        return String::new();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            while used + 1 > width {
+                used -= fitted.pop().and_then(|c| c.width()).unwrap_or(0);
+            }
+            fitted.push('…');
+            used += 1;
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    fitted + &" ".repeat(width - used)
+}
+
+const SCORE_WIDTH: usize = 5;
+
+/// One aligned line per problem: id, name, source, score, solves and tags, fitted to `width` columns.
+fn rows(problems: &[ProblemSummary], width: usize) -> Vec<String> {
+    let widest = |cell: fn(&ProblemSummary) -> String, limit: usize| {
+        problems
+            .iter()
+            .map(|p| cell(p).width())
+            .max()
+            .unwrap_or(0)
+            .min(limit)
+    };
+    let id = widest(|p| format!("#{}", p.id), 8);
+    let mut name = widest(|p| p.name.clone(), 36);
+    let mut source = widest(|p| p.source(), 24);
+    let mut solves = widest(|p| p.solves(), 14);
+
+    // The selection marker and the gaps between the six columns.
+    let fixed = 2 + 5 * 2 + id + SCORE_WIDTH;
+    let mut overflow = (fixed + name + source + solves).saturating_sub(width);
+    for (column, minimum) in [(&mut source, 0), (&mut solves, 0), (&mut name, 10)] {
+        let taken = overflow.min(column.saturating_sub(minimum));
+        *column -= taken;
+        overflow -= taken;
+    }
+    let tags = width.saturating_sub(fixed + name + source + solves);
+
+    problems
+        .iter()
+        .map(|p| {
+            format!(
+                "{}  {}  {}  {}  {}  {}",
+                fit(&format!("#{}", p.id), id).bright_black(),
+                fit(&p.name, name),
+                fit(&p.source(), source).cyan(),
+                p.score(SCORE_WIDTH),
+                fit(&p.solves(), solves),
+                fit(&p.tags(), tags).trim_end().bright_black()
+            )
+        })
+        .collect()
+}
+
+/// One page of search results.
+pub(crate) struct Results {
+    pub problems: Vec<ProblemSummary>,
+    /// How many pages there are, when the site says.
+    pub pages: Option<u32>,
+}
+
+const PAGE_SIZE: u32 = 50;
+
+#[derive(Deserialize)]
+struct SearchData {
+    problems: Vec<ProblemSummary>,
+    count: u64,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    data: SearchData,
+}
+
+/// Asks the search API for one page of problems.
+pub(crate) fn search_api(
     client: &Client,
     query: &str,
-    page: u8,
-) -> Result<Vec<ProblemSummary>, Box<dyn std::error::Error>> {
-    let response = client
-        .get("https://kilonova.ro/problems")
-        .query(&[("q", query), ("page", &page.to_string())])
+    page: u32,
+    filters: &Filters,
+) -> Result<Results, Box<dyn std::error::Error>> {
+    // The progress filters are about the logged in user.
+    let response = problem::authorized(client.post("https://kilonova.ro/api/problem/search"))
+        .header("Content-Type", "application/json")
+        .body(filters.search_body(query, page, PAGE_SIZE).to_string())
+        .send()?;
+    if !response.status().is_success() {
+        return Err(format!("{}: {}", response.status(), response.text()?.trim()).into());
+    }
+    let text = response.text()?;
+    let api_response: SearchResponse = serde_json::from_str(&text).map_err(|e| {
+        format!(
+            "JSON parsing error: '{}' on text starting with: '{}'",
+            e,
+            text.chars().take(100).collect::<String>()
+        )
+    })?;
+    Ok(Results {
+        problems: api_response.data.problems,
+        pages: Some((api_response.data.count.div_ceil(PAGE_SIZE as u64) as u32).max(1)),
+    })
+}
+
+/// Reads the results embedded in the problems page, for when the API isn't available.
+fn scrape_summaries(
+    client: &Client,
+    query: &str,
+    page: u32,
+    filters: &Filters,
+) -> Result<Results, Box<dyn std::error::Error>> {
+    let mut parameters = vec![("q", query.to_string()), ("page", page.to_string())];
+    parameters.extend(filters.query());
+    let response = problem::authorized(client.get("https://kilonova.ro/problems"))
+        .query(&parameters)
         .send()?;
     let body = response.text()?;
     let document = Html::parse_document(&body);
@@ -51,38 +233,262 @@ fn fetch_summaries(
         .ok_or("No <kn-pb-search enc> element found")?;
     let enc = elem.value().attr("enc").ok_or("Missing `enc` attribute")?;
     let raw = general_purpose::STANDARD.decode(enc)?;
-    let summaries = serde_json::from_slice(&raw)?;
-    Ok(summaries)
+    let problems = serde_json::from_slice(&raw)?;
+    Ok(Results {
+        problems,
+        pages: None,
+    })
+}
+
+/// Tries the search API first and the problems page second, and reports both failures.
+fn fetch_summaries(
+    client: &Client,
+    query: &str,
+    page: u32,
+    filters: &Filters,
+) -> Result<Results, String> {
+    search_api(client, query, page, filters).or_else(|api_error| {
+        scrape_summaries(client, query, page, filters).map_err(|page_error| {
+            format!(
+                "the search API failed ({}) and so did reading the problems page ({})",
+                api_error, page_error
+            )
+        })
+    })
 }
 
 fn draw_line(out: &mut Stdout, text: &str, row: u16, is_selected: bool) {
     execute!(out, cursor::MoveTo(0, row), Clear(ClearType::CurrentLine)).unwrap();
     let prefix = if is_selected { "> " } else { "  " };
     if is_selected {
-        execute!(out, SetAttribute(Attribute::Reverse)).unwrap();
+        // The colours would end the reverse video early.
+        execute!(
+            out,
+            SetAttribute(Attribute::Reverse),
+            Print(format!("{}{}", prefix, pager::strip_ansi(text)))
+        )
+        .unwrap();
+    } else {
+        execute!(out, Print(format!("{}{}", prefix, text))).unwrap();
     }
-    execute!(out, Print(format!("{}{}", prefix, text))).unwrap();
     if is_selected {
         execute!(out, SetAttribute(Attribute::Reset)).unwrap();
     }
 }
 
-fn choose_from_list(out: &mut Stdout, v: &[ProblemSummary]) -> String {
+/// The last row of the terminal, where prompts and errors go.
+fn bottom_row() -> u16 {
+    terminal::size().map_or(23, |(_, height)| height.saturating_sub(1))
+}
+
+/// Reads a line of text on the bottom row, `None` if cancelled with Esc.
+fn prompt(out: &mut Stdout, label: &str, initial: &str) -> Option<String> {
+    let row = bottom_row();
+    let mut text = initial.to_string();
+    execute!(out, cursor::Show).unwrap();
+    let result = loop {
+        execute!(
+            out,
+            cursor::MoveTo(0, row),
+            Clear(ClearType::CurrentLine),
+            Print(format!("{}: {}", label, text))
+        )
+        .unwrap();
+        out.flush().unwrap();
+        if let Event::Key(key) = event::read().expect("Failed to read event") {
+            match key.code {
+                KeyCode::Enter => break Some(text),
+                KeyCode::Esc => break None,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+                KeyCode::Backspace => {
+                    text.pop();
+                }
+                KeyCode::Char(c) => text.push(c),
+                _ => {}
+            }
+        }
+    };
+    execute!(
+        out,
+        cursor::Hide,
+        cursor::MoveTo(0, row),
+        Clear(ClearType::CurrentLine)
+    )
+    .unwrap();
+    result
+}
+
+fn show_error(out: &mut Stdout, message: &str) {
+    execute!(
+        out,
+        cursor::MoveTo(0, bottom_row()),
+        Clear(ClearType::CurrentLine),
+        Print(message.red())
+    )
+    .unwrap();
+    out.flush().unwrap();
+}
+
+/// Applies the filter toggles, returns whether the results have to be fetched again.
+fn change_filters(out: &mut Stdout, client: &Client, key: KeyCode, filters: &mut Filters) -> bool {
+    let names = |tags: &[(u64, String)]| {
+        tags.iter()
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match key {
+        KeyCode::Char('s') => filters.cycle_sort(),
+        KeyCode::Char('r') => filters.descending = !filters.descending,
+        KeyCode::Char('p') => filters.cycle_progress(),
+        KeyCode::Char('c') => *filters = Filters::default(),
+        KeyCode::Char('t') => {
+            let Some(text) = prompt(out, "Tags (comma separated)", &names(&filters.tags)) else {
+                return false;
+            };
+            let tags: Vec<String> = text.split(',').map(str::to_string).collect();
+            let source = filters.source.as_ref().map(|(_, name)| name.clone());
+            if let Err(e) = filters.resolve(client, &tags, source.as_deref()) {
+                show_error(out, &e);
+                return false;
+            }
+        }
+        KeyCode::Char('o') => {
+            let current = filters
+                .source
+                .as_ref()
+                .map_or("", |(_, name)| name.as_str());
+            let Some(text) = prompt(out, "Source", current) else {
+                return false;
+            };
+            let tags: Vec<String> = filters.tags.iter().map(|(id, _)| id.to_string()).collect();
+            let source = Some(text.trim()).filter(|source| !source.is_empty());
+            if let Err(e) = filters.resolve(client, &tags, source) {
+                show_error(out, &e);
+                return false;
+            }
+        }
+        KeyCode::Char('l') => {
+            let current = filters.list.map_or(String::new(), |list| list.to_string());
+            let Some(text) = prompt(out, "Problem list id", &current) else {
+                return false;
+            };
+            match text.trim() {
+                "" => filters.list = None,
+                id => match id.parse() {
+                    Ok(id) => filters.list = Some(id),
+                    Err(_) => {
+                        show_error(out, &format!("{} isn't a list id", id));
+                        return false;
+                    }
+                },
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Asks for a page number, `None` if cancelled or not a valid page.
+fn jump_to_page(out: &mut Stdout, pages: Option<u32>) -> Option<String> {
+    let text = prompt(out, "Go to page", "")?;
+    match text.trim().parse::<u32>() {
+        Ok(page)
+            if page >= 1
+                && pages.is_none_or(|pages| page <= pages)
+                && filters::page_offset(page, PAGE_SIZE).is_some() =>
+        {
+            Some(format!("page {}", page))
+        }
+        _ => {
+            show_error(out, &format!("{} isn't a page", text.trim()));
+            None
+        }
+    }
+}
+
+/// The first visible result, moved just enough for `selected` to be visible.
+fn scroll_to(selected: usize, scroll: usize, height: usize) -> usize {
+    if selected < scroll {
+        selected
+    } else if selected >= scroll + height {
+        selected + 1 - height.max(1)
+    } else {
+        scroll
+    }
+}
+
+/// E.g. `page 3 of 12 | 21-40 of 50 results here`.
+fn page_indicator(
+    page: u32,
+    pages: Option<u32>,
+    shown: std::ops::Range<usize>,
+    count: usize,
+) -> String {
+    let page = match pages {
+        Some(pages) => format!("page {} of {}", page, pages),
+        None => format!("page {}", page),
+    };
+    if shown.start == 0 && shown.end >= count {
+        page
+    } else {
+        format!(
+            "{} | {}-{} of {} results here",
+            page,
+            shown.start + 1,
+            shown.end,
+            count
+        )
+    }
+}
+
+fn choose_from_list(
+    out: &mut Stdout,
+    client: &Client,
+    results: &Results,
+    page: u32,
+    // `None` for results that can't be filtered, e.g. from the offline index.
+    mut filters: Option<&mut Filters>,
+) -> String {
     while event::poll(Duration::from_millis(0)).unwrap_or(false) {
         let _ = event::read();
     }
 
+    let v = &results.problems;
+    let (instructions, description) = match &filters {
+        Some(filters) => (
+            "Controls: [f/d] scroll up/down | [j/k] next/previous page, [g] go to page | [s]ort, [r]everse, [p]rogress, [t]ags, s[o]urce, [l]ist, [c]lear filters | [q] to quit",
+            format!("Filters: {}", filters.describe()),
+        ),
+        None => (
+            "Controls: [f/d] scroll up/down | [q] to quit",
+            "Searching the offline index".to_string(),
+        ),
+    };
+    let (width, height) = terminal::size().map_or((80, 24), |(w, h)| (w as usize, h as usize));
+    execute!(
+        out,
+        cursor::MoveTo(0, 0),
+        // A wrapped line would push the results down.
+        Print(fit(instructions, width)),
+        cursor::MoveTo(0, 1),
+        Print(fit(&description, width).bright_black())
+    )
+    .unwrap();
+
     if v.is_empty() {
         execute!(
             out,
-            cursor::MoveTo(0, 0),
+            cursor::MoveTo(0, 2),
             Print("No results found on this page.")
         )
         .unwrap();
         execute!(
             out,
-            cursor::MoveTo(0, 2),
-            Print("Press k for back, or Esc/q to quit.")
+            cursor::MoveTo(0, 4),
+            Print(
+                "Press k for back, g to go to another page, change the filters, or Esc/q to quit."
+            )
         )
         .unwrap();
         out.flush().unwrap();
@@ -94,168 +500,163 @@ fn choose_from_list(out: &mut Stdout, v: &[ProblemSummary]) -> String {
                     KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                         return "esc".into();
                     }
+                    KeyCode::Char('g') => {
+                        if let Some(choice) = jump_to_page(out, results.pages) {
+                            return choice;
+                        }
+                    }
+                    code if filters
+                        .as_deref_mut()
+                        .is_some_and(|filters| change_filters(out, client, code, filters)) =>
+                    {
+                        return "filters".into();
+                    }
                     _ => {}
                 }
             }
         }
     }
 
-    let instructions = "Controls: [f/d] scroll up/down | [j/k] next/previous page, [q] to quit";
-    execute!(out, cursor::MoveTo(0, 0), Print(instructions)).unwrap();
-
     let mut selected_idx: usize = 0;
+    let mut scroll: usize = 0;
     let mut last_input_time = Instant::now();
     let cooldown = Duration::from_millis(100);
 
-    for (i, p) in v.iter().enumerate() {
-        // Offset drawing by 2 rows to make space for instructions and a blank line
-        draw_line(out, &p.name, (i + 2) as u16, i == selected_idx);
-    }
-    out.flush().unwrap();
-
-    loop {
-        if let Event::Key(key) = event::read().expect("Failed to read event") {
-            if last_input_time.elapsed() >= cooldown {
-                let prev_idx = selected_idx;
-                match key.code {
-                    KeyCode::Up | KeyCode::Char('f') => {
-                        if selected_idx > 0 {
-                            selected_idx -= 1;
-                        }
-                    }
-                    KeyCode::Down | KeyCode::Char('d') => {
-                        if selected_idx + 1 < v.len() {
-                            selected_idx += 1;
-                        }
-                    }
-                    KeyCode::Left | KeyCode::Char('k') => return "left".into(),
-                    KeyCode::Right | KeyCode::Char('j') => return "right".into(),
-                    KeyCode::Enter => return v[selected_idx].id.to_string(),
-                    KeyCode::Esc | KeyCode::Char('q') => return "esc".into(),
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        return "esc".into();
-                    }
-                    _ => {}
-                }
-                last_input_time = Instant::now();
-                if selected_idx != prev_idx {
-                    // Also offset these drawing calls by 2 rows
-                    draw_line(out, &v[prev_idx].name, (prev_idx + 2) as u16, false);
-                    draw_line(out, &v[selected_idx].name, (selected_idx + 2) as u16, true);
-                    out.flush().unwrap();
-                }
+    // The instructions and the filters above, the page indicator below.
+    let visible = height.saturating_sub(3).max(1);
+    let lines = rows(v, width);
+    let draw_all = |out: &mut Stdout, selected_idx: usize, scroll: usize| {
+        for row in 0..visible {
+            let i = scroll + row;
+            match lines.get(i) {
+                Some(line) => draw_line(out, line, (row + 2) as u16, i == selected_idx),
+                None => execute!(
+                    out,
+                    cursor::MoveTo(0, (row + 2) as u16),
+                    Clear(ClearType::CurrentLine)
+                )
+                .unwrap(),
             }
         }
-    }
-}
-
-struct Pager<'a> {
-    lines: Vec<&'a str>,
-    top_line: usize,
-    start_row: u16,
-    view_height: u16,
-    should_quit: bool,
-}
-
-impl<'a> Pager<'a> {
-    fn new(content: &'a str) -> Self {
-        Self {
-            lines: content.lines().collect(),
-            top_line: 0,
-            start_row: 0,   // Will be set at runtime
-            view_height: 0, // Will be set at runtime
-            should_quit: false,
-        }
-    }
-
-    fn run(&mut self) -> Result<(), std::io::Error> {
-        let mut out = stdout();
-        enable_raw_mode()?;
-
-        self.start_row = cursor::position()?.1;
-        let (_, term_height) = terminal::size()?;
-        self.view_height = term_height.saturating_sub(self.start_row).max(1);
-
-        while !self.should_quit {
-            self.render(&mut out)?;
-            self.handle_input()?;
-        }
-
-        self.render(&mut out)?;
-        let final_row = self.start_row
-            + self
-                .lines
-                .iter()
-                .skip(self.top_line)
-                .take(self.view_height as usize)
-                .count() as u16;
-        execute!(out, cursor::MoveTo(0, final_row), Print("\n"))?;
-
-        disable_raw_mode()
-    }
-
-    fn render(&self, out: &mut Stdout) -> Result<(), std::io::Error> {
-        execute!(out, cursor::SavePosition)?;
+        let shown = scroll..(scroll + visible).min(v.len());
         execute!(
             out,
-            cursor::MoveTo(0, self.start_row),
-            Clear(ClearType::FromCursorDown)
-        )?;
-
-        let visible_lines = self
-            .lines
-            .iter()
-            .skip(self.top_line)
-            .take(self.view_height as usize);
-        for (i, line) in visible_lines.enumerate() {
-            execute!(
-                out,
-                cursor::MoveTo(0, self.start_row + i as u16),
-                Print(line)
-            )?;
-        }
-        execute!(out, cursor::RestorePosition)?;
-        out.flush()
-    }
+            cursor::MoveTo(0, (visible + 2) as u16),
+            Clear(ClearType::CurrentLine),
+            Print(page_indicator(page, results.pages, shown, v.len()).bright_black())
+        )
+        .unwrap();
+        out.flush().unwrap();
+    };
+    draw_all(out, selected_idx, scroll);
 
-    fn handle_input(&mut self) -> Result<(), std::io::Error> {
-        if let Event::Key(key) = event::read()? {
+    loop {
+        if let Event::Key(key) = event::read().expect("Failed to read event")
+            && last_input_time.elapsed() >= cooldown
+        {
+            let prev_idx = selected_idx;
             match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
-                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    self.should_quit = true;
+                KeyCode::Up | KeyCode::Char('f') => {
+                    selected_idx = selected_idx.saturating_sub(1);
                 }
-                KeyCode::Up | KeyCode::Char('k') => {
-                    self.top_line = self.top_line.saturating_sub(1);
+                KeyCode::Down | KeyCode::Char('d') if selected_idx + 1 < v.len() => {
+                    selected_idx += 1;
                 }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    let max_top_line = self.lines.len().saturating_sub(self.view_height as usize);
-                    if self.top_line < max_top_line {
-                        self.top_line += 1;
-                    }
+                KeyCode::Left | KeyCode::Char('k') => return "left".into(),
+                KeyCode::Right | KeyCode::Char('j') => return "right".into(),
+                KeyCode::Enter => return v[selected_idx].id.to_string(),
+                KeyCode::Esc | KeyCode::Char('q') => return "esc".into(),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return "esc".into();
                 }
-                KeyCode::PageUp => {
-                    self.top_line = self.top_line.saturating_sub(self.view_height as usize);
+                KeyCode::Char('g') => {
+                    if let Some(choice) = jump_to_page(out, results.pages) {
+                        return choice;
+                    }
                 }
-                KeyCode::PageDown | KeyCode::Char(' ') => {
-                    let max_top_line = self.lines.len().saturating_sub(self.view_height as usize);
-                    let new_top = self.top_line.saturating_add(self.view_height as usize);
-                    self.top_line = new_top.min(max_top_line);
+                code if filters
+                    .as_deref_mut()
+                    .is_some_and(|filters| change_filters(out, client, code, filters)) =>
+                {
+                    return "filters".into();
                 }
                 _ => {}
             }
+            last_input_time = Instant::now();
+            let new_scroll = scroll_to(selected_idx, scroll, visible);
+            if new_scroll != scroll {
+                scroll = new_scroll;
+                draw_all(out, selected_idx, scroll);
+            } else if selected_idx != prev_idx {
+                // Rows are offset by the instructions and the filters
+                draw_line(out, &lines[prev_idx], (prev_idx - scroll + 2) as u16, false);
+                draw_line(
+                    out,
+                    &lines[selected_idx],
+                    (selected_idx - scroll + 2) as u16,
+                    true,
+                );
+                out.flush().unwrap();
+            }
         }
-        Ok(())
     }
 }
 
-pub(crate) fn display_problem(id: &str) {
-    credential_manager::CredentialManager::global()
-        .set::<credential_manager::Cache>(id)
-        .expect("Couldn't save the id token");
+/// Searches the problems downloaded by `index sync`, without touching the network.
+pub fn search_offline(name: &str) {
+    let catalogue = match catalogue::Catalogue::load() {
+        Ok(catalogue) => catalogue,
+        Err(e) => {
+            println!("{} {}", "Couldn't read the offline index:".red(), e);
+            return;
+        }
+    };
+    if catalogue.problems.is_empty() {
+        println!(
+            "{}",
+            "The offline index is empty, run `index sync` first".yellow()
+        );
+        return;
+    }
+    let results = Results {
+        problems: catalogue::matches(&catalogue.problems, name)
+            .into_iter()
+            .cloned()
+            .collect(),
+        pages: Some(1),
+    };
+
+    let client = Client::new();
+    let mut out = stdout();
+    enable_raw_mode().expect("Failed to enable raw mode");
+    execute!(out, cursor::Hide).expect("Failed to hide cursor");
+    let final_choice = loop {
+        execute!(out, Clear(ClearType::All), cursor::MoveTo(0, 0)).expect("Failed to clear screen");
+        let choice = choose_from_list(&mut out, &client, &results, 1, None);
+        // There's only one page, so paging keys do nothing.
+        if !matches!(choice.as_str(), "left" | "right") && !choice.starts_with("page ") {
+            break choice;
+        }
+    };
+    execute!(
+        out,
+        Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        cursor::Show
+    )
+    .unwrap();
+    disable_raw_mode().expect("Failed to disable raw mode");
+
+    if final_choice != "esc" {
+        display_problem(&final_choice, false);
+    }
+}
 
+/// Shows a problem's statement, toggling between its translations with `t`.
+/// With `both`, the Romanian and English statements are shown side by side.
+pub(crate) fn display_problem(id: &str, both: bool) {
     let client = Client::new();
-    let mut statement_files = ["statement-ro.md", "statement-en.md"];
+    let mut statement_files = [("ro", "statement-ro.md"), ("en", "statement-en.md")];
     let preferred_language = credential_manager::CredentialManager::global()
         .get::<credential_manager::StatementLanguage>();
     if let Some("en") = preferred_language.as_deref() {
@@ -264,52 +665,97 @@ pub(crate) fn display_problem(id: &str) {
 
     println!("Searching for problem statement for ID: {}...", id);
 
-    let statement_content: Option<String> = statement_files.iter().find_map(|&file_name| {
-        let fetch_and_decode = || -> Result<String, Box<dyn std::error::Error>> {
-            let url = format!(
-                "https://kilonova.ro/api/problem/{}/get/attachmentByName/{}",
-                id, file_name
-            );
-            let response = client.get(url).send()?;
-            if !response.status().is_success() {
-                return Err("Failed to fetch from API".into());
-            }
-            let text = response.text()?;
-            if text.trim().is_empty() {
-                return Err("Empty response".into());
-            }
-            let api_response: ApiResponse = serde_json::from_str(&text).map_err(|e| {
-                format!(
-                    "JSON parsing error: '{}' on text starting with: '{}'",
-                    e,
-                    text.chars().take(100).collect::<String>()
-                )
-            })?;
-            let decoded_bytes = general_purpose::STANDARD.decode(api_response.data.data)?;
-            String::from_utf8(decoded_bytes).map_err(|e| e.into())
-        };
-        fetch_and_decode().ok()
-    });
+    // Every translation is fetched up front, so switching between them is instant. Mirrored
+    // problems are read from the archive when the site can't be reached.
+    let info = info::fetch(&client, id).ok().or_else(|| archive::info(id));
+    let header = info.as_ref().map(info::markdown).unwrap_or_default();
+    let mut archived = false;
+    let statements: Vec<(String, String)> = statement_files
+        .iter()
+        .filter_map(|&(language, file_name)| {
+            let bytes = problem::attachment(&client, id, file_name)
+                .ok()
+                .or_else(|| {
+                    let bytes = archive::attachment(id, file_name)?;
+                    archived = true;
+                    Some(bytes)
+                })?;
+            String::from_utf8(bytes)
+                .ok()
+                .map(|content| (language.to_string(), format!("{}{}", header, content)))
+        })
+        .collect();
+    if archived {
+        println!("{}", "Showing the statement saved by `mirror`".yellow());
+    }
 
-    if let Some(content) = statement_content {
-        println!("\n(use j/k to scroll, q to exit)");
+    // Only problems that exist go in the history, a typo shouldn't become the last viewed one.
+    if info.is_none() && statements.is_empty() {
+        println!("\nCould not find problem ID: {}", id);
+        return;
+    }
+    let name = info.as_ref().map_or("", |info| info.details.name.as_str());
+    history::record(id, name);
 
-        let mut pager = Pager::new(&content);
-        if let Err(e) = pager.run() {
-            let _ = disable_raw_mode();
-            eprintln!("\nPager Error: {}", e);
-        }
-    } else {
+    if statements.is_empty() {
         println!(
             "\nCould not find a valid, readable statement for problem ID: {}",
             id
         );
+        return;
+    }
+    if both && statements.len() < 2 {
+        println!(
+            "{}",
+            format!("Only the {} statement is available", statements[0].0).yellow()
+        );
     }
+
+    let protocol = images::protocol();
+    // Images are downloaded once, then laid out again when the terminal is resized.
+    let downloaded: RefCell<HashMap<String, Option<Vec<u8>>>> = RefCell::new(HashMap::new());
+    let load_image = |url: &str, max_cols: usize| {
+        if protocol == images::Protocol::Off {
+            return None;
+        }
+        let mut downloaded = downloaded.borrow_mut();
+        let data = downloaded
+            .entry(url.to_string())
+            .or_insert_with(|| {
+                images::fetch(&client, id, url)
+                    .ok()
+                    .or_else(|| archive::attachment(id, images::file_name(url)))
+            })
+            .as_ref()?;
+        let height = terminal::size().map_or(24, |(_, height)| height as usize);
+        images::picture(data, protocol, max_cols, height.saturating_sub(4)).ok()
+    };
+
+    let documents: Vec<(String, pager::Layout)> = statements
+        .iter()
+        .map(|(language, content)| {
+            let load_image = &load_image;
+            let layout: pager::Layout = Box::new(move |width| {
+                markdown::render(content, width, &mut |url, cols| load_image(url, cols))
+            });
+            (language.clone(), layout)
+        })
+        .collect();
+    pager::show_all(documents, both);
 }
 
-pub fn search(name: &str) {
+pub fn search(name: &str, flags: &SearchFilters) {
     let client = Client::new();
-    let mut page: u8 = 1;
+    let mut page: u32 = 1;
+    // The last page that had results, to go back to from an empty one.
+    let mut last_page: u32 = 1;
+    let mut filters = match Filters::from_flags(&client, flags) {
+        Ok(filters) => filters,
+        Err(e) => {
+            println!("{} {}", "Couldn't apply the filters:".red(), e);
+            return;
+        }
+    };
 
     let mut out = stdout();
     enable_raw_mode().expect("Failed to enable raw mode");
@@ -318,26 +764,28 @@ pub fn search(name: &str) {
     let final_choice = loop {
         execute!(out, Clear(ClearType::All), cursor::MoveTo(0, 0)).expect("Failed to clear screen");
 
-        let summaries = match fetch_summaries(&client, name, page) {
+        let results = match fetch_summaries(&client, name, page, &filters) {
             Ok(s) => s,
-            Err(_) => {
-                if page > 1 {
-                    page = page.saturating_sub(1);
-                }
-                continue;
-            }
+            Err(e) => break Err(e),
         };
 
-        if summaries.is_empty() && page > 1 {
-            page = page.saturating_sub(1);
+        if results.problems.is_empty() && page > 1 && page != last_page {
+            page = last_page;
             continue;
         }
+        last_page = page;
 
-        let choice = choose_from_list(&mut out, &summaries);
+        let choice = choose_from_list(&mut out, &client, &results, page, Some(&mut filters));
 
         match choice.as_str() {
             "right" => {
-                page += 1;
+                if results.pages.is_none_or(|pages| page < pages) {
+                    page += 1;
+                }
+                continue;
+            }
+            "filters" => {
+                page = 1;
                 continue;
             }
             "left" => {
@@ -346,8 +794,13 @@ pub fn search(name: &str) {
                 }
                 continue;
             }
-            _ => break choice,
+            _ => {}
         }
+        if let Some(target) = choice.strip_prefix("page ") {
+            page = target.parse().unwrap_or(page);
+            continue;
+        }
+        break Ok(choice);
     };
 
     execute!(
@@ -359,7 +812,63 @@ pub fn search(name: &str) {
     .unwrap();
     disable_raw_mode().expect("Failed to disable raw mode");
 
-    if final_choice != "esc" {
-        display_problem(&final_choice);
+    match final_choice {
+        Ok(choice) if choice == "esc" => {}
+        Ok(choice) => display_problem(&choice, false),
+        Err(e) => println!("{} {}", "Couldn't search for problems:".red(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_cells_to_their_width() {
+        assert_eq!(fit("abc", 5), "abc  ");
+        assert_eq!(fit("abcdef", 4), "abc…");
+        assert_eq!(fit("日本語", 4), "日… ");
+        assert_eq!(fit("abc", 0), "");
+    }
+
+    #[test]
+    fn scrolls_to_the_selection() {
+        assert_eq!(scroll_to(3, 0, 10), 0);
+        assert_eq!(scroll_to(10, 0, 10), 1);
+        assert_eq!(scroll_to(2, 5, 10), 2);
+        assert_eq!(page_indicator(3, Some(12), 0..5, 5), "page 3 of 12");
+        assert_eq!(
+            page_indicator(300, None, 20..40, 50),
+            "page 300 | 21-40 of 50 results here"
+        );
+    }
+
+    #[test]
+    fn aligns_result_rows() {
+        let summary: Vec<ProblemSummary> = serde_json::from_str(
+            r#"[
+                {"id": 7, "name": "sum", "max_score": 100, "solved_by": 1234,
+                 "tags": [{"name": "ONI 2024", "type": "contest"}, {"name": "math", "type": "method"}]},
+                {"id": 1234, "name": "a much longer name", "max_score": -1}
+            ]"#,
+        )
+        .unwrap();
+        let lines: Vec<String> = rows(&summary, 80)
+            .iter()
+            .map(|line| pager::strip_ansi(line))
+            .collect();
+        assert_eq!(
+            lines[0],
+            "#7     sum                 ONI 2024  100 ●  1234 solves  math"
+        );
+        assert_eq!(
+            lines[1],
+            "#1234  a much longer name                ·               "
+        );
+        assert!(
+            rows(&summary, 40)
+                .iter()
+                .all(|line| 2 + pager::strip_ansi(line).width() <= 40)
+        );
     }
 }
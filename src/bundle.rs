@@ -0,0 +1,340 @@
+use super::rust_bundle;
+use colored::Colorize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single-file version of a solution, along with every local file that went into it.
+pub(crate) struct Bundle {
+    pub source: String,
+    pub files: Vec<PathBuf>,
+}
+
+struct CppBundler<'a> {
+    include_dirs: &'a [PathBuf],
+    included_once: HashSet<PathBuf>,
+    stack: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    output: String,
+}
+
+fn local_include(line: &str) -> Option<&str> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = directive.strip_prefix("include")?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    rest.split_once('"').map(|(name, _)| name)
+}
+
+fn is_pragma_once(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|rest| rest.split_whitespace().eq(["pragma", "once"]))
+}
+
+/// Copies the string or character literal starting at `start` into `output`,
+/// returning where it ends.
+fn copy_literal(chars: &[char], start: usize, output: &mut String) -> usize {
+    let quote = chars[start];
+    output.push(quote);
+    let mut i = start + 1;
+    while let Some(&c) = chars.get(i) {
+        output.push(c);
+        i += 1;
+        if c == '\\' {
+            output.extend(chars.get(i));
+            i += 1;
+        } else if c == quote || c == '\n' {
+            break;
+        }
+    }
+    i
+}
+
+/// Whether the `'` at `i` starts a character literal, rather than being a C++ digit
+/// separator (`1'000'000`) or a Rust lifetime (`'a`).
+fn is_char_literal(chars: &[char], i: usize) -> bool {
+    let after_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+    match chars.get(i + 1) {
+        _ if after_word => false,
+        Some('\\') => true,
+        Some(&c) => c != '\'' && chars.get(i + 2) == Some(&'\''),
+        None => false,
+    }
+}
+
+/// `content` with its `//` and `/* */` comments turned into spaces, keeping the line breaks
+/// so its lines still match the original ones.
+pub(crate) fn strip_comments(content: &str) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let mut output = String::with_capacity(content.len());
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        let next = chars.get(i + 1).copied();
+        match c {
+            '/' if next == Some('/') => {
+                while chars.get(i).is_some_and(|&c| c != '\n') {
+                    i += 1;
+                }
+                output.push(' ');
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        output.push('\n');
+                    }
+                    i += 1;
+                }
+                i += 2;
+                output.push(' ');
+            }
+            '"' => i = copy_literal(&chars, i, &mut output),
+            '\'' if is_char_literal(&chars, i) => i = copy_literal(&chars, i, &mut output),
+            _ => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    output
+}
+
+/// The name and the rest of a preprocessor directive, e.g. `("if", "0")`.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    Some((&rest[..end], rest[end..].trim()))
+}
+
+/// Keeps track of the `#if 0` blocks, whose lines the compiler never sees.
+#[derive(Default)]
+struct DisabledBlocks {
+    depth: usize,
+}
+
+impl DisabledBlocks {
+    /// Whether `line` is compiled, given the lines before it.
+    fn is_live(&mut self, line: &str) -> bool {
+        let Some((name, rest)) = directive(line) else {
+            return self.depth == 0;
+        };
+        match name {
+            "if" if self.depth == 0 && rest == "0" => self.depth = 1,
+            _ if self.depth == 0 => return true,
+            "if" | "ifdef" | "ifndef" => self.depth += 1,
+            "endif" => self.depth -= 1,
+            "else" | "elif" if self.depth == 1 => self.depth = 0,
+            _ => {}
+        }
+        false
+    }
+}
+
+/// `#ifndef X` / `#define X` as the first directives and `#endif` as the last one.
+fn has_include_guard(content: &str) -> bool {
+    let content = strip_comments(content);
+    let code: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    fn argument<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+        directive(line).and_then(|(found, rest)| (found == name).then_some(rest))
+    }
+    match (code.first(), code.get(1), code.last()) {
+        (Some(first), Some(second), Some(last)) => {
+            let guard = argument(first, "ifndef");
+            guard.is_some()
+                && guard == argument(second, "define")
+                && argument(last, "endif").is_some()
+        }
+        _ => false,
+    }
+}
+
+impl CppBundler<'_> {
+    fn resolve(&self, name: &str, from: &Path) -> Option<PathBuf> {
+        let dir = from.parent().unwrap_or(Path::new("."));
+        std::iter::once(dir)
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+    }
+
+    fn inline(&mut self, path: &Path) -> Result<(), String> {
+        if self.included_once.contains(path) {
+            return Ok(());
+        }
+        if self.stack.iter().any(|p| p == path) {
+            return Err(format!("{} includes itself", path.display()));
+        }
+
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if has_include_guard(&content) {
+            self.included_once.insert(path.to_path_buf());
+        }
+        if !self.files.iter().any(|p| p == path) {
+            self.files.push(path.to_path_buf());
+        }
+        self.stack.push(path.to_path_buf());
+
+        let code = strip_comments(&content);
+        let mut disabled = DisabledBlocks::default();
+        for (line, code) in content.lines().zip(code.lines()) {
+            if !disabled.is_live(code) {
+                self.output.push_str(line);
+                self.output.push('\n');
+                continue;
+            }
+            if is_pragma_once(code) {
+                self.included_once.insert(path.to_path_buf());
+                continue;
+            }
+            // Headers that aren't found locally, e.g. "bits/stdc++.h", are left to the compiler.
+            match local_include(code).and_then(|name| Some((name, self.resolve(name, path)?))) {
+                Some((name, header)) => {
+                    if self.included_once.contains(&header) {
+                        continue;
+                    }
+                    self.output.push_str(&format!("// begin \"{}\"\n", name));
+                    self.inline(&header)?;
+                    self.output.push_str(&format!("// end \"{}\"\n", name));
+                }
+                None => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+            }
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Inlines the local `#include "..."` headers of a C++ source, recursively.
+/// Headers with `#pragma once` or an include guard are only inlined the first time,
+/// system headers, headers that aren't found and includes in comments or `#if 0` blocks
+/// are left as they are.
+pub(crate) fn bundle_cpp(path: &Path, include_dirs: &[PathBuf]) -> Result<Bundle, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut bundler = CppBundler {
+        include_dirs,
+        included_once: HashSet::new(),
+        stack: vec![],
+        files: vec![],
+        output: String::new(),
+    };
+    bundler.inline(&path)?;
+    Ok(Bundle {
+        source: bundler.output,
+        files: bundler.files,
+    })
+}
+
+/// Bundles a C++ or Rust solution, depending on its extension.
+/// `libraries` are the `name=path` local crates a Rust solution uses.
+pub(crate) fn bundle_file(
+    path: &Path,
+    include_dirs: &[PathBuf],
+    libraries: &[String],
+) -> Result<Bundle, String> {
+    if path.extension().is_some_and(|ext| ext == "rs") {
+        let libraries = libraries
+            .iter()
+            .map(|spec| rust_bundle::parse_library(spec))
+            .collect::<Result<Vec<_>, _>>()?;
+        rust_bundle::bundle_rust(path, &libraries)
+    } else {
+        bundle_cpp(path, include_dirs)
+    }
+}
+
+pub fn bundle(
+    path: PathBuf,
+    output: Option<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    libraries: Vec<String>,
+) {
+    let bundle = match bundle_file(&path, &include_dirs, &libraries) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            println!("{} {}", "Couldn't bundle your solution :".red(), e);
+            return;
+        }
+    };
+
+    match output {
+        Some(output) => match fs::write(&output, &bundle.source) {
+            Ok(()) => println!(
+                "{}",
+                format!(
+                    "Bundled {} files into {}",
+                    bundle.files.len(),
+                    output.display()
+                )
+                .green()
+            ),
+            Err(e) => println!("{} {}", "Couldn't write the bundle :".red(), e),
+        },
+        None => print!("{}", bundle.source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn inlines_local_headers_once() {
+        let dir = TestDir::new("bundle-cpp");
+        dir.write("lib/dsu.h", "#pragma once\nstruct Dsu {};\n");
+        dir.write(
+            "lib/seg.h",
+            "#ifndef SEG_H\n#define SEG_H\n#include \"dsu.h\"\nstruct Seg {};\n#endif\n",
+        );
+        let main = dir.write("main.cpp",
+            "#include <bits/stdc++.h>\n#include \"lib/dsu.h\"\n#include \"lib/seg.h\"\n#include \"lib/dsu.h\"\nint main() {}\n",
+        );
+
+        let bundle = bundle_cpp(&main, &[]).unwrap();
+        assert_eq!(bundle.files.len(), 3);
+        assert_eq!(bundle.source.matches("struct Dsu").count(), 1);
+        assert_eq!(bundle.source.matches("struct Seg").count(), 1);
+        assert!(bundle.source.contains("#include <bits/stdc++.h>"));
+        assert!(!bundle.source.contains("#pragma once"));
+        assert!(!bundle.source.contains("#include \""));
+    }
+
+    #[test]
+    fn detects_include_guards() {
+        assert!(has_include_guard(
+            "// lib\n#ifndef A\n#define A\nint x;\n#endif\n"
+        ));
+        assert!(has_include_guard(
+            "/*\n * MIT License\n */\n#ifndef A\n#define A\nint x;\n#endif // A\n"
+        ));
+        assert!(!has_include_guard("#ifndef A\n#define B\nint x;\n#endif\n"));
+    }
+
+    #[test]
+    fn leaves_disabled_and_unknown_includes_alone() {
+        let dir = TestDir::new("bundle-disabled");
+        dir.write("debug.h", "struct Debug {};\n");
+        let main = dir.write("main.cpp",
+            "#include \"bits/stdc++.h\"\n/*\n#include \"debug.h\"\n*/\n#if 0\n#include \"debug.h\"\n#else\nint x;\n#endif\nint main() {}\n",
+        );
+
+        let bundle = bundle_cpp(&main, &[]).unwrap();
+        assert_eq!(bundle.files.len(), 1);
+        assert!(!bundle.source.contains("struct Debug"));
+        assert!(bundle.source.contains("#include \"bits/stdc++.h\""));
+        assert_eq!(bundle.source.matches("#include \"debug.h\"").count(), 2);
+    }
+}
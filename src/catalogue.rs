@@ -0,0 +1,215 @@
+use super::browser::{self, ProblemSummary};
+use super::filters::Filters;
+use super::storage;
+use colored::Colorize;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::io::{Write, stdout};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE: &str = "problems.json";
+
+/// Every problem on the site, as downloaded by `index sync`.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Catalogue {
+    /// Seconds since the Unix epoch.
+    pub synced_at: u64,
+    pub problems: Vec<ProblemSummary>,
+}
+
+impl Catalogue {
+    pub(crate) fn load() -> Result<Self, String> {
+        storage::load(FILE)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        storage::save(FILE, self)
+    }
+}
+
+/// Every problem matching the filters, going through all the pages of results.
+pub(crate) fn fetch_all(
+    client: &Client,
+    filters: &Filters,
+) -> Result<Vec<ProblemSummary>, Box<dyn std::error::Error>> {
+    let mut problems = vec![];
+    let mut page = 1;
+    loop {
+        let results = browser::search_api(client, "", page, filters)?;
+        let last = results.problems.is_empty() || results.pages.is_none_or(|pages| page >= pages);
+        problems.extend(results.problems);
+        print!("\rFound {} problems", problems.len());
+        let _ = stdout().flush();
+        if last {
+            println!();
+            return Ok(problems);
+        }
+        page += 1;
+    }
+}
+
+pub fn sync() {
+    let problems = match fetch_all(&Client::new(), &Filters::default()) {
+        Ok(problems) => problems,
+        Err(e) => {
+            println!();
+            println!("{} {}", "Couldn't download the problems:".red(), e);
+            return;
+        }
+    };
+
+    let catalogue = Catalogue {
+        synced_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        problems,
+    };
+    match catalogue.save() {
+        Ok(()) => println!(
+            "{}",
+            format!(
+                "Saved {} problems for offline search",
+                catalogue.problems.len()
+            )
+            .green()
+        ),
+        Err(e) => println!("{} {}", "Couldn't save the problems:".red(), e),
+    }
+}
+
+/// Lowercases and drops the Romanian diacritics, both the comma and the cedilla forms.
+pub(crate) fn fold(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'ă' | 'â' => 'a',
+            'î' => 'i',
+            'ș' | 'ş' => 's',
+            'ț' | 'ţ' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+fn words(text: &str) -> Vec<String> {
+    fold(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Optimal string alignment distance: edits, with swapping two neighbours counting as one.
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>(); a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// How well a query word matches a word of the problem, `None` when it doesn't.
+fn word_score(query: &str, word: &str) -> Option<u32> {
+    if word == query {
+        return Some(10);
+    }
+    if word.starts_with(query) {
+        return Some(7);
+    }
+    if query.len() >= 3 && word.contains(query) {
+        return Some(5);
+    }
+    let query: Vec<char> = query.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    // Longer words are allowed more typos.
+    let allowed = match query.len() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    };
+    // Typos in a prefix of the word too, so that `grdina` still finds `gradinarul`. A missing
+    // or an extra letter makes the prefix one longer or shorter.
+    let typos = (query.len().saturating_sub(1)..=query.len() + 1)
+        .filter(|&length| length < word.len())
+        .map(|length| distance(&query, &word[..length]))
+        .fold(distance(&query, &word), usize::min);
+    (typos <= allowed).then(|| 4 - typos as u32)
+}
+
+/// Every query word has to match the name, the source or a tag, the name counting the most.
+fn score(problem: &ProblemSummary, query: &[String]) -> Option<u32> {
+    let name = words(&problem.name);
+    let rest: Vec<String> = problem
+        .tags
+        .iter()
+        .flat_map(|tag| words(&tag.name))
+        .chain(words(&problem.source_credits))
+        .collect();
+    let best =
+        |words: &[String], query: &str| words.iter().filter_map(|w| word_score(query, w)).max();
+
+    query.iter().try_fold(0, |total, word| {
+        if *word == problem.id.to_string() {
+            return Some(total + 20);
+        }
+        let matched = best(&name, word)
+            .map(|score| score * 2)
+            .max(best(&rest, word))?;
+        Some(total + matched)
+    })
+}
+
+/// The problems matching `query`, best first.
+pub(crate) fn matches<'a>(problems: &'a [ProblemSummary], query: &str) -> Vec<&'a ProblemSummary> {
+    let query = words(query);
+    let mut scored: Vec<(u32, &ProblemSummary)> = problems
+        .iter()
+        .filter_map(|problem| Some((score(problem, &query)?, problem)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.id.cmp(&b.1.id)));
+    scored.into_iter().map(|(_, problem)| problem).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(id: u64, name: &str) -> ProblemSummary {
+        serde_json::from_value(serde_json::json!({ "id": id, "name": name })).unwrap()
+    }
+
+    #[test]
+    fn folds_romanian_diacritics() {
+        assert_eq!(fold("Șir ÎNȚELEPT ţăran"), "sir intelept taran");
+        assert_eq!(words("Grădină-mică (2)"), vec!["gradina", "mica", "2"]);
+    }
+
+    #[test]
+    fn tolerates_typos() {
+        let problems = vec![
+            problem(1, "Grădina"),
+            problem(2, "Drumuri minime"),
+            problem(3, "Graf"),
+            problem(4, "Grădinarul"),
+        ];
+        let ids =
+            |query: &str| -> Vec<u64> { matches(&problems, query).iter().map(|p| p.id).collect() };
+        assert_eq!(ids("gradina"), vec![1, 4]);
+        assert_eq!(ids("grdina"), vec![1, 4]);
+        assert_eq!(ids("drumrui minim"), vec![2]);
+        assert_eq!(ids("3"), vec![3]);
+        assert!(ids("xyz").is_empty());
+    }
+}
@@ -16,6 +16,7 @@ pub fn setup() {
 
     spinner.stop();
     let user_info = user_info::get_user();
+    let logged_in = user_info.is_ok();
     let user_info = match user_info {
         Ok(user) => {
             match logging::extend_session() {
@@ -32,6 +33,10 @@ pub fn setup() {
 
     println!("{}", kn_status);
     println!("{}", user_info);
+
+    if logged_in && !queue::is_empty() {
+        queue::flush();
+    }
 }
 
 mod tests {
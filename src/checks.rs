@@ -0,0 +1,280 @@
+use super::problem::ProblemDetails;
+use super::{bundle, runner};
+use serde::Deserialize;
+
+/// The `checks` section of the configuration.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct ChecksConfig {
+    pub enabled: bool,
+    /// Overrides the problem's own source size limit, in bytes.
+    pub max_source_size: Option<u64>,
+    pub forbidden: Vec<String>,
+    pub debug_output: Vec<String>,
+    pub file_io: bool,
+    pub syntax_check: bool,
+}
+
+impl Default for ChecksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_source_size: None,
+            forbidden: ["system(", "fork(", "popen(", "execv"]
+                .map(String::from)
+                .to_vec(),
+            debug_output: [
+                "cerr",
+                "clog",
+                "fprintf(stderr",
+                "dbg!",
+                "eprintln!",
+                "eprint!",
+                "sys.stderr",
+                "console.error",
+            ]
+            .map(String::from)
+            .to_vec(),
+            file_io: true,
+            syntax_check: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Failure {
+    pub check: &'static str,
+    pub message: String,
+}
+
+/// Python `source` with its `#` comments turned into spaces, keeping the line breaks.
+fn strip_python_comments(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut output = String::with_capacity(source.len());
+    // The quote character and how many of them close the string, 3 for `"""`.
+    let mut string: Option<(char, usize)> = None;
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        let repeated = |count: usize, quote: char| {
+            (0..count).all(|offset| chars.get(i + offset) == Some(&quote))
+        };
+        match string {
+            Some(_) if c == '\\' => {
+                output.push(c);
+                output.extend(chars.get(i + 1));
+                i += 2;
+                continue;
+            }
+            Some((quote, count)) if repeated(count, quote) => {
+                output.extend(&chars[i..i + count]);
+                i += count;
+                string = None;
+                continue;
+            }
+            Some((_, 1)) if c == '\n' => string = None,
+            Some(_) => {}
+            None if c == '#' => {
+                while chars.get(i).is_some_and(|&c| c != '\n') {
+                    i += 1;
+                }
+                output.push(' ');
+                continue;
+            }
+            None if c == '"' || c == '\'' => {
+                let count = if repeated(3, c) { 3 } else { 1 };
+                output.extend(&chars[i..i + count]);
+                i += count;
+                string = Some((c, count));
+                continue;
+            }
+            None => {}
+        }
+        output.push(c);
+        i += 1;
+    }
+    output
+}
+
+/// `(line number, pattern)` for every line containing one of the patterns outside of
+/// comments, `#` ones for Python and `//` or `/* */` ones for everything else.
+/// Preprocessor lines are code too, e.g. `#define dbg(x) cerr << x`.
+fn find_patterns<'a>(
+    source: &str,
+    language: &str,
+    patterns: &'a [String],
+) -> Vec<(usize, &'a str)> {
+    let code = match language {
+        "python3" => strip_python_comments(source),
+        _ => bundle::strip_comments(source),
+    };
+    code.lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            patterns
+                .iter()
+                .filter(move |pattern| line.contains(pattern.as_str()))
+                .map(move |pattern| (i + 1, pattern.as_str()))
+        })
+        .collect()
+}
+
+fn describe(found: &[(usize, &str)]) -> String {
+    found
+        .iter()
+        .map(|(line, pattern)| format!("`{}` on line {}", pattern, line))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn check_file_io(source: &str, problem: &ProblemDetails) -> Option<String> {
+    if problem.console_input {
+        let opens_files = source.contains(".in\"") || source.contains(".out\"");
+        return opens_files.then(|| {
+            "the problem uses standard input/output, but the code opens .in/.out files".into()
+        });
+    }
+
+    let missing: Vec<String> = ["in", "out"]
+        .iter()
+        .map(|ext| format!("{}.{}", problem.test_name, ext))
+        .filter(|name| !source.contains(name.as_str()))
+        .collect();
+    (!missing.is_empty()).then(|| {
+        format!(
+            "the problem reads from files, but the code never mentions {}",
+            missing.join(" and ")
+        )
+    })
+}
+
+/// Runs every enabled check on the source that is about to be submitted.
+/// The caller skips it altogether when `enabled` is off.
+pub(crate) fn run(
+    config: &ChecksConfig,
+    source: &str,
+    language: &str,
+    problem: Option<&ProblemDetails>,
+) -> Vec<Failure> {
+    let mut failures = vec![];
+
+    let limit = config
+        .max_source_size
+        .or(problem.map(|p| p.source_size).filter(|&size| size > 0));
+    if let Some(limit) = limit
+        && source.len() as u64 > limit
+    {
+        failures.push(Failure {
+            check: "source size",
+            message: format!("{} bytes, the limit is {} bytes", source.len(), limit),
+        });
+    }
+
+    let forbidden = find_patterns(source, language, &config.forbidden);
+    if !forbidden.is_empty() {
+        failures.push(Failure {
+            check: "forbidden constructs",
+            message: describe(&forbidden),
+        });
+    }
+
+    let debug = find_patterns(source, language, &config.debug_output);
+    if !debug.is_empty() {
+        failures.push(Failure {
+            check: "debug output",
+            message: describe(&debug),
+        });
+    }
+
+    if config.file_io
+        && let Some(problem) = problem.filter(|p| !p.test_name.is_empty() || p.console_input)
+        && let Some(message) = check_file_io(source, problem)
+    {
+        failures.push(Failure {
+            check: "file I/O",
+            message,
+        });
+    }
+
+    if config.syntax_check
+        && let Err(message) = runner::syntax_check(source, language)
+    {
+        failures.push(Failure {
+            check: "syntax",
+            message: message.trim_end().to_string(),
+        });
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(console_input: bool) -> ProblemDetails {
+        ProblemDetails {
+            source_size: 100,
+            console_input,
+            test_name: "sum".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flags_debug_output_outside_comments() {
+        let source = "int main() {\n    // cerr << x;\n    cerr << x;\n}\n";
+        let failures = run(&ChecksConfig::default(), source, "cpp17", None);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].check, "debug output");
+        assert!(failures[0].message.contains("line 3"));
+    }
+
+    #[test]
+    fn scans_preprocessor_lines_but_not_block_comments() {
+        let source = "#include <iostream>\n/*\ncerr << x;\n*/\n#define dbg(x) cerr << x\n";
+        let failures = run(&ChecksConfig::default(), source, "cpp17", None);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].message, "`cerr` on line 5");
+    }
+
+    #[test]
+    fn uses_python_comments_for_python() {
+        let source = "# sys.stderr.write(a)\nprint(a // 2, file=sys.stderr)\ns = \"# not a comment\" # cerr\n";
+        let failures = run(&ChecksConfig::default(), source, "python3", None);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].message, "`sys.stderr` on line 2");
+    }
+
+    #[test]
+    fn skips_digit_separators_and_lifetimes() {
+        let cpp = "int n = 1'000'000; cerr << n; // clog\nchar c = '\\''; clog << c;\n";
+        let failures = run(&ChecksConfig::default(), cpp, "cpp17", None);
+        assert_eq!(failures[0].message, "`cerr` on line 1, `clog` on line 2");
+
+        let rust = "fn f<'a>(x: &'a str) { eprintln!(\"{}\", x); } // dbg!\n";
+        let failures = run(&ChecksConfig::default(), rust, "rust", None);
+        assert_eq!(failures[0].message, "`eprintln!` on line 1");
+    }
+
+    #[test]
+    fn checks_file_names_and_size() {
+        let source = "freopen(\"sum.in\", \"r\", stdin);";
+        let failures = run(
+            &ChecksConfig::default(),
+            source,
+            "cpp17",
+            Some(&problem(false)),
+        );
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].message.contains("sum.out"));
+
+        let long = "x".repeat(200);
+        let failures = run(
+            &ChecksConfig::default(),
+            &long,
+            "cpp17",
+            Some(&problem(true)),
+        );
+        assert_eq!(failures[0].check, "source size");
+    }
+}
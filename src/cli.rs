@@ -1,5 +1,7 @@
-use clap::{Parser, Subcommand};
+use crate::filters::{Progress, Sort};
+use clap::{Args, Parser, Subcommand};
 use std::ffi::OsString;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -11,6 +13,54 @@ pub struct Cli {
     pub command: Commands,
 }
 
+#[derive(Debug, Args)]
+pub struct SearchFilters {
+    #[clap(
+        long = "tag",
+        value_name = "TAG",
+        help = "Only show problems with this tag, by name or id"
+    )]
+    pub tags: Vec<String>,
+    #[clap(
+        long,
+        value_enum,
+        help = "Only show problems you solved, attempted or didn't solve"
+    )]
+    pub progress: Option<Progress>,
+    #[clap(long, help = "Only show problems from this contest or olympiad")]
+    pub source: Option<String>,
+    #[clap(
+        long,
+        value_name = "ID",
+        help = "Only show problems from this problem list"
+    )]
+    pub list: Option<u64>,
+    #[clap(long, value_enum, default_value = "id", help = "Sort the results")]
+    pub sort: Sort,
+    #[clap(long, help = "Sort in descending order")]
+    pub descending: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct MirrorSelection {
+    #[clap(value_name = "ID", help = "Problems to mirror")]
+    pub ids: Vec<u64>,
+    #[clap(
+        long,
+        value_name = "ID",
+        help = "Mirror every problem of this problem list"
+    )]
+    pub list: Option<u64>,
+    #[clap(
+        long = "tag",
+        value_name = "TAG",
+        help = "Mirror every problem with this tag, by name or id"
+    )]
+    pub tags: Vec<String>,
+    #[clap(long, help = "Mirror every problem on the site")]
+    pub all: bool,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     #[clap(about = "Check the status of the API and extend your login session token")]
@@ -26,10 +76,59 @@ pub enum Commands {
     Me,
 
     #[clap(about = "Search for a problem by name")]
-    Search { name: String },
+    Search {
+        name: String,
+        #[clap(
+            long,
+            conflicts_with_all = ["tags", "progress", "source", "list", "descending"],
+            help = "Search the problems saved by `index sync` instead of the site"
+        )]
+        offline: bool,
+        #[command(flatten)]
+        filters: SearchFilters,
+    },
 
-    #[clap(about = "Submit a solution to the last viewed problem")]
-    Submit { path: OsString },
+    #[clap(
+        about = "Submit a solution to the last viewed problem (a directory of NN.out files for output-only problems)"
+    )]
+    Submit {
+        path: OsString,
+        #[clap(
+            long,
+            value_name = "PROBLEM",
+            help = "Problem id, or @1, @2... from `recent`, instead of the last viewed problem"
+        )]
+        problem: Option<String>,
+        #[clap(
+            long,
+            help = "Bundle local C++ headers or Rust modules and crates into one file before submitting"
+        )]
+        bundle: bool,
+        #[clap(
+            short = 'I',
+            long = "include-dir",
+            help = "Extra directory to look for headers in"
+        )]
+        include_dirs: Vec<PathBuf>,
+        #[clap(
+            long = "lib",
+            value_name = "NAME=PATH",
+            help = "Local Rust crate to inline as a module"
+        )]
+        libraries: Vec<String>,
+        #[clap(
+            long,
+            help = "Submit even if the same code was already submitted, or if the outputs of an output-only problem can't be checked"
+        )]
+        force: bool,
+        #[clap(long, help = "Submit even if the pre-submit checks fail")]
+        skip_checks: bool,
+        #[clap(
+            long,
+            help = "Save the submission in the offline queue instead of uploading it"
+        )]
+        queue: bool,
+    },
 
     #[clap(about = "Set the default language for submissions")]
     SetLanguage { name: String },
@@ -37,6 +136,115 @@ pub enum Commands {
     #[clap(about = "Set the default language for statements")]
     SetStatementLanguage { name: String },
 
-    #[clap(about = "View the last seen problem statement")]
-    View,
+    #[clap(about = "Show a problem's limits, source, tags and your score")]
+    Info { id: String },
+
+    #[clap(
+        about = "View the last seen problem statement",
+        after_help = "Keys: j/k scroll, / and ? search forward and backward, n/N next and previous hit, \
+                      g/G top and bottom, [/] previous and next section, i/o/e jump to the input, \
+                      output and examples sections, t switch the language, q quit"
+    )]
+    View {
+        #[clap(
+            value_name = "PROBLEM",
+            help = "Problem id, or @1, @2... from `recent`, instead of the last viewed problem"
+        )]
+        problem: Option<String>,
+        #[clap(long, help = "Show the Romanian and English statements side by side")]
+        both: bool,
+    },
+
+    #[clap(about = "Run a solution against local tests and show a diff of failed outputs")]
+    Test {
+        path: OsString,
+        #[clap(
+            long,
+            default_value = "tests",
+            help = "Directory with <name>.in / <name>.out pairs"
+        )]
+        tests: PathBuf,
+        #[clap(long, default_value_t = 1.0, help = "Time limit per test, in seconds")]
+        time_limit: f64,
+        #[clap(
+            long,
+            help = "Interactor (executable or C++ source) to run the solution against"
+        )]
+        interactor: Option<OsString>,
+        #[clap(
+            long,
+            help = "Rerun the tests every time the solution or its headers change"
+        )]
+        watch: bool,
+        #[clap(
+            long,
+            requires = "watch",
+            help = "Submit the solution once every local test passes"
+        )]
+        submit_on_pass: bool,
+    },
+
+    #[clap(about = "Download the attachments (sample tests, graders) of the last viewed problem")]
+    Download {
+        #[clap(long, default_value = ".")]
+        dir: PathBuf,
+    },
+
+    #[clap(about = "Bundle a multi-file C++ or Rust solution into a single file")]
+    Bundle {
+        path: PathBuf,
+        #[clap(short, long, help = "Write the bundle here instead of printing it")]
+        output: Option<PathBuf>,
+        #[clap(
+            short = 'I',
+            long = "include-dir",
+            help = "Extra directory to look for headers in"
+        )]
+        include_dirs: Vec<PathBuf>,
+        #[clap(
+            long = "lib",
+            value_name = "NAME=PATH",
+            help = "Local Rust crate to inline as a module"
+        )]
+        libraries: Vec<String>,
+    },
+
+    #[clap(about = "Manage the local copy of the problem list used by `search --offline`")]
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+
+    #[clap(about = "Save statements, sample tests and details of problems for offline use")]
+    Mirror {
+        #[command(flatten)]
+        selection: MirrorSelection,
+    },
+
+    #[clap(about = "Search the statements of the mirrored problems")]
+    Grep { pattern: String },
+
+    #[clap(about = "List the recently viewed problems and open one of them")]
+    Recent,
+
+    #[clap(about = "Manage submissions saved while offline")]
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QueueAction {
+    #[clap(about = "Upload the queued submissions in order and show their verdicts")]
+    Flush,
+
+    #[clap(about = "List the queued submissions")]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IndexAction {
+    #[clap(about = "Download every problem's name, source, tags and your score")]
+    Sync,
 }
@@ -0,0 +1,77 @@
+use super::checks::ChecksConfig;
+use super::hooks::HooksConfig;
+use super::storage;
+use serde::Deserialize;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const USER_FILE: &str = "config.json";
+const WORKSPACE_FILE: &str = "kilo-cli.json";
+
+/// Settings read from `config.json` in the data directory, overridden field by field
+/// by a `kilo-cli.json` in the current directory or any of its parents.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub(crate) struct Config {
+    pub checks: ChecksConfig,
+    pub hooks: HooksConfig,
+}
+
+fn read(path: &Path) -> Result<Option<Value>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+pub(crate) fn workspace_file() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(WORKSPACE_FILE))
+        .find(|path| path.is_file())
+}
+
+pub(crate) fn load() -> Result<Config, String> {
+    let mut config = read(&storage::data_dir().join(USER_FILE))?.unwrap_or(Value::Null);
+    if let Some(path) = workspace_file()
+        && let Some(workspace) = read(&path)?
+    {
+        merge(&mut config, workspace);
+    }
+    if config.is_null() {
+        return Ok(Config::default());
+    }
+    serde_json::from_value(config).map_err(|e| format!("Invalid configuration: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn workspace_overrides_single_fields() {
+        let mut config = json!({ "checks": { "syntax_check": true, "forbidden": ["system("] } });
+        merge(&mut config, json!({ "checks": { "forbidden": [] } }));
+        assert_eq!(
+            config,
+            json!({ "checks": { "syntax_check": true, "forbidden": [] } })
+        );
+    }
+}
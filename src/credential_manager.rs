@@ -47,7 +47,7 @@ impl CredentialManager {
 
     pub fn global() -> &'static Self {
         static INSTANCE: OnceLock<CredentialManager> = OnceLock::new();
-        INSTANCE.get_or_init(|| CredentialManager::new())
+        INSTANCE.get_or_init(CredentialManager::new)
     }
 
     fn get_entry<T: IsService>(&self) -> Option<Entry> {
@@ -0,0 +1,284 @@
+use colored::{Color, Colorize};
+
+const MAX_LINES: usize = 500;
+const MAX_LINE_WIDTH: usize = 200;
+const CONTEXT: usize = 3;
+
+/// Where the first token-wise difference between two outputs is.
+/// Lines and tokens are 1-indexed, `None` means the output ended early.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Mismatch {
+    pub expected_line: usize,
+    pub expected_token: usize,
+    pub actual_line: usize,
+    pub actual_token: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    text.lines().enumerate().flat_map(|(line, content)| {
+        content
+            .split_whitespace()
+            .enumerate()
+            .map(move |(token, word)| (line + 1, token + 1, word))
+    })
+}
+
+pub(crate) fn first_mismatch(expected: &str, actual: &str) -> Option<Mismatch> {
+    let mut expected_tokens = tokens(expected);
+    let mut actual_tokens = tokens(actual);
+    loop {
+        match (expected_tokens.next(), actual_tokens.next()) {
+            (None, None) => return None,
+            (Some((_, _, e)), Some((_, _, a))) if e == a => continue,
+            (e, a) => {
+                let end_of = |text: &str| (text.lines().count().max(1), 0);
+                let (expected_line, expected_token) =
+                    e.map_or_else(|| end_of(expected), |t| (t.0, t.1));
+                let (actual_line, actual_token) = a.map_or_else(|| end_of(actual), |t| (t.0, t.1));
+                return Some(Mismatch {
+                    expected_line,
+                    expected_token,
+                    actual_line,
+                    actual_token,
+                    expected: e.map(|t| t.2.to_string()),
+                    actual: a.map(|t| t.2.to_string()),
+                });
+            }
+        }
+    }
+}
+
+fn same_line(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
+
+fn truncate(line: &str) -> String {
+    if line.chars().count() <= MAX_LINE_WIDTH {
+        return line.to_string();
+    }
+    let mut short: String = line.chars().take(MAX_LINE_WIDTH).collect();
+    short.push('…');
+    short
+}
+
+fn highlight_token(line: &str, token: usize, color: Color) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    let mut current = 0;
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        current += 1;
+        if current == token {
+            result.push_str(&rest[..end].color(color).bold().reversed().to_string());
+        } else {
+            result.push_str(&rest[..end].color(color).to_string());
+        }
+        rest = &rest[end..];
+    }
+    result
+}
+
+enum Op {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+fn diff_ops(expected: &[&str], actual: &[&str]) -> Vec<Op> {
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same_line(expected[i], actual[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same_line(expected[i], actual[j]) {
+            ops.push(Op::Same(i));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Removed(i));
+            i += 1;
+        } else {
+            ops.push(Op::Added(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Renders a unified diff of `expected` against `actual`, coloured for the terminal.
+/// Whitespace-only differences are ignored, like the judge's default checker does.
+pub(crate) fn unified(expected: &str, actual: &str) -> String {
+    let mismatch = first_mismatch(expected, actual);
+    let mut out = vec![];
+
+    match &mismatch {
+        None => {
+            out.push("Outputs are identical".green().to_string());
+            return out.join("\n");
+        }
+        Some(m) => out.push(format!(
+            "First difference on line {}, token {}: expected {}, found {}",
+            if m.expected.is_some() {
+                m.expected_line
+            } else {
+                m.actual_line
+            },
+            if m.expected.is_some() {
+                m.expected_token
+            } else {
+                m.actual_token
+            },
+            m.expected
+                .as_deref()
+                .map_or("end of output".into(), |t| format!("`{}`", t)),
+            m.actual
+                .as_deref()
+                .map_or("end of output".into(), |t| format!("`{}`", t)),
+        )),
+    }
+
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = actual.lines().collect();
+
+    let prefix = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .take_while(|(e, a)| same_line(e, a))
+        .count();
+    let suffix = expected_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(actual_lines[prefix..].iter().rev())
+        .take_while(|(e, a)| same_line(e, a))
+        .count();
+    let start = prefix.saturating_sub(CONTEXT);
+    let expected_end = (expected_lines.len() - suffix + CONTEXT).min(expected_lines.len());
+    let actual_end = (actual_lines.len() - suffix + CONTEXT).min(actual_lines.len());
+
+    let mut truncated = false;
+    let expected_window = &expected_lines[start..expected_end];
+    let actual_window = &actual_lines[start..actual_end];
+    let expected_window = if expected_window.len() > MAX_LINES {
+        truncated = true;
+        &expected_window[..MAX_LINES]
+    } else {
+        expected_window
+    };
+    let actual_window = if actual_window.len() > MAX_LINES {
+        truncated = true;
+        &actual_window[..MAX_LINES]
+    } else {
+        actual_window
+    };
+
+    out.push("--- expected".red().to_string());
+    out.push("+++ actual".green().to_string());
+    out.push(
+        format!(
+            "@@ -{},{} +{},{} @@",
+            start + 1,
+            expected_window.len(),
+            start + 1,
+            actual_window.len()
+        )
+        .cyan()
+        .to_string(),
+    );
+
+    let highlighted = |line_number: usize, line: &str, is_expected: bool| -> String {
+        let line = truncate(line);
+        let color = if is_expected {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        let token = match &mismatch {
+            Some(m) if is_expected && m.expected_line == line_number && m.expected.is_some() => {
+                m.expected_token
+            }
+            Some(m) if !is_expected && m.actual_line == line_number && m.actual.is_some() => {
+                m.actual_token
+            }
+            _ => 0,
+        };
+        highlight_token(&line, token, color)
+    };
+
+    for op in diff_ops(expected_window, actual_window) {
+        match op {
+            Op::Same(i) => out.push(format!(" {}", truncate(expected_window[i]))),
+            Op::Removed(i) => out.push(format!(
+                "{}{}",
+                "-".red(),
+                highlighted(start + i + 1, expected_window[i], true)
+            )),
+            Op::Added(j) => out.push(format!(
+                "{}{}",
+                "+".green(),
+                highlighted(start + j + 1, actual_window[j], false)
+            )),
+        }
+    }
+
+    if truncated {
+        out.push(
+            format!("... output truncated to {} lines", MAX_LINES)
+                .yellow()
+                .to_string(),
+        );
+    }
+
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_whitespace_differences() {
+        assert_eq!(first_mismatch("1 2\n3\n", "1   2 \n3"), None);
+    }
+
+    #[test]
+    fn finds_first_differing_token() {
+        let mismatch = first_mismatch("1 2\n3 4\n", "1 2\n3 5\n").unwrap();
+        assert_eq!(mismatch.expected_line, 2);
+        assert_eq!(mismatch.actual_line, 2);
+        assert_eq!(mismatch.expected_token, 2);
+        assert_eq!(mismatch.actual_token, 2);
+        assert_eq!(mismatch.expected.as_deref(), Some("4"));
+        assert_eq!(mismatch.actual.as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn reports_missing_output() {
+        let mismatch = first_mismatch("1 2 3", "1 2").unwrap();
+        assert_eq!(mismatch.expected_token, 3);
+        assert_eq!(mismatch.expected.as_deref(), Some("3"));
+        assert_eq!(mismatch.actual, None);
+    }
+
+    #[test]
+    fn truncates_huge_outputs() {
+        let expected: String = (0..2000).map(|i| format!("{}\n", i)).collect();
+        let actual: String = (0..2000).map(|i| format!("{}\n", i + 1)).collect();
+        let diff = unified(&expected, &actual);
+        assert!(diff.contains("output truncated"));
+        assert!(diff.lines().count() < 2 * MAX_LINES + 10);
+    }
+}
@@ -0,0 +1,283 @@
+use super::cli::SearchFilters;
+use super::problem::{self, Tag};
+use clap::ValueEnum;
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+
+/// Which problems to show, by what the logged in user did on them.
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+pub enum Progress {
+    Solved,
+    Attempted,
+    Unsolved,
+}
+
+impl Progress {
+    fn name(self) -> &'static str {
+        match self {
+            Progress::Solved => "solved",
+            Progress::Attempted => "attempted",
+            Progress::Unsolved => "unsolved",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, ValueEnum)]
+pub enum Sort {
+    #[default]
+    Id,
+    Name,
+    Popularity,
+    Difficulty,
+}
+
+impl Sort {
+    fn name(self) -> &'static str {
+        match self {
+            Sort::Id => "id",
+            Sort::Name => "name",
+            Sort::Popularity => "popularity",
+            Sort::Difficulty => "difficulty",
+        }
+    }
+
+    /// The ordering the problem browser calls it by.
+    fn key(self) -> &'static str {
+        match self {
+            Sort::Id => "id",
+            Sort::Name => "name",
+            Sort::Popularity => "hot",
+            Sort::Difficulty => "score",
+        }
+    }
+}
+
+/// The filters of the Kilonova problem browser.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Filters {
+    /// Tag ids with their names.
+    pub tags: Vec<(u64, String)>,
+    pub progress: Option<Progress>,
+    /// A contest tag, e.g. `ONI 2024`.
+    pub source: Option<(u64, String)>,
+    pub list: Option<u64>,
+    pub sort: Sort,
+    pub descending: bool,
+}
+
+/// Finds a tag by id or by name, ignoring case. `kind` restricts it to one type of tag.
+fn find_tag(all: &[Tag], wanted: &str, kind: Option<&str>) -> Result<(u64, String), String> {
+    let wanted = wanted.trim();
+    all.iter()
+        .filter(|tag| kind.is_none_or(|kind| tag.kind == kind))
+        .find(|tag| tag.id.to_string() == wanted || tag.name.eq_ignore_ascii_case(wanted))
+        .map(|tag| (tag.id, tag.name.clone()))
+        .ok_or_else(|| format!("There's no tag called {}", wanted))
+}
+
+impl Filters {
+    pub(crate) fn from_flags(client: &Client, flags: &SearchFilters) -> Result<Self, String> {
+        let mut filters = Filters {
+            progress: flags.progress,
+            list: flags.list,
+            sort: flags.sort,
+            descending: flags.descending,
+            ..Default::default()
+        };
+        filters.resolve(client, &flags.tags, flags.source.as_deref())?;
+        Ok(filters)
+    }
+
+    /// Turns the tag and source names into the ids the problem browser filters by.
+    pub(crate) fn resolve(
+        &mut self,
+        client: &Client,
+        tags: &[String],
+        source: Option<&str>,
+    ) -> Result<(), String> {
+        if tags.is_empty() && source.is_none() {
+            self.tags.clear();
+            self.source = None;
+            return Ok(());
+        }
+        let all = problem::all_tags(client).map_err(|e| e.to_string())?;
+        self.tags = tags
+            .iter()
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| find_tag(&all, name, None))
+            .collect::<Result<_, _>>()?;
+        self.source = source
+            .map(|name| find_tag(&all, name, Some("contest")))
+            .transpose()?;
+        Ok(())
+    }
+
+    /// The query parameters of the problems page.
+    pub(crate) fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = vec![];
+        let tags: Vec<String> = self
+            .tags
+            .iter()
+            .chain(&self.source)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        if !tags.is_empty() {
+            query.push(("tags", tags.join(",")));
+        }
+        if let Some(progress) = self.progress {
+            query.push(("progress", progress.name().to_string()));
+        }
+        if let Some(list) = self.list {
+            query.push(("list_id", list.to_string()));
+        }
+        if self.sort != Sort::Id || self.descending {
+            query.push(("ord", self.sort.key().to_string()));
+            query.push(("desc", self.descending.to_string()));
+        }
+        query
+    }
+
+    /// The body of a search API request for one page of `page_size` problems.
+    pub(crate) fn search_body(&self, query: &str, page: u32, page_size: u32) -> Value {
+        let mut body = json!({
+            "name_fuzzy": query,
+            "ordering": self.sort.key(),
+            "descending": self.descending,
+            "limit": page_size,
+            // Twice as wide as the page number, so it can't wrap.
+            "offset": u64::from(page.max(1) - 1) * u64::from(page_size),
+        });
+        // Each group has to match, so every tag is a group of its own.
+        let tags: Vec<Value> = self
+            .tags
+            .iter()
+            .chain(&self.source)
+            .map(|(id, _)| json!({ "tags": [id], "negate": false }))
+            .collect();
+        if !tags.is_empty() {
+            body["tags"] = Value::Array(tags);
+        }
+        if let Some(progress) = self.progress {
+            body["progress"] = json!(progress.name());
+        }
+        if let Some(list) = self.list {
+            body["deep_list_id"] = json!(list);
+        }
+        body
+    }
+
+    pub(crate) fn cycle_sort(&mut self) {
+        self.sort = match self.sort {
+            Sort::Id => Sort::Name,
+            Sort::Name => Sort::Popularity,
+            Sort::Popularity => Sort::Difficulty,
+            Sort::Difficulty => Sort::Id,
+        };
+    }
+
+    pub(crate) fn cycle_progress(&mut self) {
+        self.progress = match self.progress {
+            None => Some(Progress::Solved),
+            Some(Progress::Solved) => Some(Progress::Attempted),
+            Some(Progress::Attempted) => Some(Progress::Unsolved),
+            Some(Progress::Unsolved) => None,
+        };
+    }
+
+    /// A one-line summary for the top of the results.
+    pub(crate) fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "sorted by {}{}",
+            self.sort.name(),
+            if self.descending { " (descending)" } else { "" }
+        )];
+        if !self.tags.is_empty() {
+            let names: Vec<&str> = self.tags.iter().map(|(_, name)| name.as_str()).collect();
+            parts.push(format!("tags: {}", names.join(", ")));
+        }
+        if let Some((_, source)) = &self.source {
+            parts.push(format!("source: {}", source));
+        }
+        if let Some(progress) = self.progress {
+            parts.push(progress.name().to_string());
+        }
+        if let Some(list) = self.list {
+            parts.push(format!("list #{}", list));
+        }
+        parts.join(" | ")
+    }
+}
+
+/// The index of the first problem on `page`, `None` when it doesn't fit the API's counter.
+pub(crate) fn page_offset(page: u32, page_size: u32) -> Option<u32> {
+    (page.max(1) - 1).checked_mul(page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_problems_query() {
+        assert!(Filters::default().query().is_empty());
+
+        let filters = Filters {
+            tags: vec![(4, "dp".into()), (9, "greedy".into())],
+            source: Some((31, "ONI 2024".into())),
+            progress: Some(Progress::Unsolved),
+            sort: Sort::Popularity,
+            descending: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.query(),
+            vec![
+                ("tags", "4,9,31".to_string()),
+                ("progress", "unsolved".to_string()),
+                ("ord", "hot".to_string()),
+                ("desc", "true".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_the_search_body() {
+        let filters = Filters {
+            tags: vec![(4, "dp".into())],
+            list: Some(12),
+            sort: Sort::Name,
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.search_body("sum", 3, 50),
+            json!({
+                "name_fuzzy": "sum",
+                "ordering": "name",
+                "descending": false,
+                "limit": 50,
+                "offset": 100,
+                "tags": [{ "tags": [4], "negate": false }],
+                "deep_list_id": 12,
+            })
+        );
+        assert_eq!(
+            filters.search_body("", u32::MAX, 50)["offset"],
+            json!((u64::from(u32::MAX) - 1) * 50)
+        );
+        assert_eq!(page_offset(3, 50), Some(100));
+        assert_eq!(page_offset(u32::MAX, 50), None);
+    }
+
+    #[test]
+    fn finds_tags_by_name_or_id() {
+        let tag = |id: u64, name: &str, kind: &str| Tag {
+            id,
+            name: name.into(),
+            kind: kind.into(),
+        };
+        let all = vec![tag(4, "DP", "method"), tag(31, "ONI 2024", "contest")];
+        assert_eq!(find_tag(&all, "dp", None), Ok((4, "DP".to_string())));
+        assert_eq!(find_tag(&all, "31", Some("contest")).map(|t| t.0), Ok(31));
+        assert!(find_tag(&all, "dp", Some("contest")).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use super::{credential_manager, queue, storage, view};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE: &str = "history.json";
+/// How many problems are remembered.
+const LIMIT: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub id: String,
+    pub name: String,
+    /// Seconds since the Unix epoch.
+    pub viewed_at: u64,
+}
+
+/// The recently viewed problems, newest first.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct History {
+    pub entries: Vec<Entry>,
+}
+
+impl History {
+    pub(crate) fn load() -> Result<Self, String> {
+        storage::load(FILE)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        storage::save(FILE, self)
+    }
+
+    /// Moves the problem to the front, dropping the oldest entries past the limit.
+    fn push(&mut self, entry: Entry) {
+        self.entries.retain(|e| e.id != entry.id);
+        self.entries.insert(0, entry);
+        self.entries.truncate(LIMIT);
+    }
+
+    /// The id `@n` refers to, `@1` being the last viewed problem.
+    fn get(&self, reference: &str) -> Result<String, String> {
+        let position: usize = reference
+            .parse()
+            .map_err(|_| format!("@{} isn't a history entry, they look like @1", reference))?;
+        match position.checked_sub(1).and_then(|i| self.entries.get(i)) {
+            Some(entry) => Ok(entry.id.clone()),
+            None => Err(format!(
+                "There's no @{}, `recent` lists the history",
+                position
+            )),
+        }
+    }
+}
+
+/// Remembers a viewed problem. The last one is also kept in the keyring for older versions.
+pub(crate) fn record(id: &str, name: &str) {
+    credential_manager::CredentialManager::global()
+        .set::<credential_manager::Cache>(id)
+        .expect("Couldn't save the id token");
+
+    let mut history = History::load().unwrap_or_default();
+    history.push(Entry {
+        id: id.to_string(),
+        name: name.to_string(),
+        viewed_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    });
+    if let Err(e) = history.save() {
+        println!("{} {}", "Couldn't save the history:".red(), e);
+    }
+}
+
+/// The problem a command refers to: an id, `@n` from the history, or the last viewed problem.
+pub(crate) fn resolve(reference: Option<&str>) -> Result<String, String> {
+    match reference.map(str::trim) {
+        Some(reference) if reference.starts_with('@') => History::load()?.get(&reference[1..]),
+        Some(id) if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) => Ok(id.to_string()),
+        Some(other) => Err(format!(
+            "{} isn't a problem id or a history entry like @1",
+            other
+        )),
+        None => credential_manager::CredentialManager::global()
+            .get::<credential_manager::Cache>()
+            .ok_or_else(|| "You need to look at a problem first".to_string()),
+    }
+}
+
+/// Lists the recently viewed problems and opens the one picked.
+pub fn recent() {
+    let history = match History::load() {
+        Ok(history) => history,
+        Err(e) => {
+            println!("{} {}", "Couldn't read the history:".red(), e);
+            return;
+        }
+    };
+    if history.entries.is_empty() {
+        println!("{}", "You haven't viewed any problems yet".yellow());
+        return;
+    }
+    for (position, entry) in history.entries.iter().enumerate() {
+        println!(
+            "{:>4} {} {} ({})",
+            format!("@{}", position + 1).cyan(),
+            format!("#{}", entry.id).bright_black(),
+            entry.name,
+            queue::age(entry.viewed_at)
+        );
+    }
+    if !io::stdin().is_terminal() {
+        return;
+    }
+
+    print!("Open which one? (number, Enter to cancel) ");
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() || answer.trim().is_empty() {
+        return;
+    }
+    match history.get(answer.trim().trim_start_matches('@')) {
+        Ok(id) => view::show(&id, false),
+        Err(e) => println!("{}", e.yellow()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> Entry {
+        Entry {
+            id: id.into(),
+            name: format!("problem {}", id),
+            viewed_at: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_recent_problems_once() {
+        let mut history = History::default();
+        for id in ["1", "2", "1", "3"] {
+            history.push(entry(id));
+        }
+        let ids: Vec<&str> = history.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["3", "1", "2"]);
+        assert_eq!(history.get("2"), Ok("1".to_string()));
+        assert!(history.get("4").is_err());
+        assert!(history.get("0").is_err());
+        assert!(history.get("x").is_err());
+
+        for id in 0..LIMIT + 5 {
+            history.push(entry(&id.to_string()));
+        }
+        assert_eq!(history.entries.len(), LIMIT);
+    }
+}
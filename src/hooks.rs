@@ -0,0 +1,130 @@
+use colored::Colorize;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The `hooks` section of the configuration, shell commands run around `submit`.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct HooksConfig {
+    /// Gets the source path and language as arguments, a non-zero exit cancels the submission.
+    #[serde(alias = "pre-submit")]
+    pub pre_submit: Vec<String>,
+    /// Gets the judged submission's JSON on stdin.
+    #[serde(alias = "post-verdict")]
+    pub post_verdict: Vec<String>,
+}
+
+pub(crate) struct HookContext<'a> {
+    pub problem: &'a str,
+    pub source: &'a Path,
+    pub language: &'a str,
+}
+
+/// Runs `command` through the shell as written, with `args` available as `$1`, `$2`...
+/// `cmd` has no positional arguments, so on Windows hooks only get the environment variables.
+fn shell(command: &str, args: &[&str]) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command).arg("kilo-cli").args(args);
+        shell
+    }
+}
+
+fn with_context(mut command: Command, context: &HookContext) -> Command {
+    command
+        .env("KILO_PROBLEM", context.problem)
+        .env("KILO_SOURCE", context.source)
+        .env("KILO_LANGUAGE", context.language);
+    command
+}
+
+pub(crate) fn pre_submit(hooks: &HooksConfig, context: &HookContext) -> Result<(), String> {
+    let source = context.source.to_string_lossy();
+    for hook in &hooks.pre_submit {
+        let status = with_context(shell(hook, &[&source, context.language]), context)
+            .status()
+            .map_err(|e| format!("`{}` couldn't start: {}", hook, e))?;
+        if !status.success() {
+            return Err(format!("`{}` exited with {}", hook, status));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn post_verdict(
+    hooks: &HooksConfig,
+    context: &HookContext,
+    submission: u64,
+    json: &str,
+) {
+    for hook in &hooks.post_verdict {
+        let mut command = with_context(shell(hook, &[]), context);
+        command
+            .env("KILO_SUBMISSION", submission.to_string())
+            .stdin(Stdio::piped());
+        let result = command.spawn().and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(json.as_bytes());
+            }
+            child.wait()
+        });
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!(
+                "{} `{}` exited with {}",
+                "post-verdict hook failed :".yellow(),
+                hook,
+                status
+            ),
+            Err(e) => println!(
+                "{} `{}`: {}",
+                "post-verdict hook failed :".yellow(),
+                hook,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_pre_submit_hooks_verbatim() {
+        let context = HookContext {
+            problem: "1",
+            source: Path::new("main.cpp"),
+            language: "cpp17",
+        };
+        let hooks = |command: &str| HooksConfig {
+            pre_submit: vec![command.to_string()],
+            post_verdict: vec![],
+        };
+        // Arguments appended after the command instead of passed as `$1` and `$2` would make `[` fail.
+        assert!(
+            pre_submit(
+                &hooks("[ \"$1\" = main.cpp ] && [ \"$2\" = cpp17 ]"),
+                &context
+            )
+            .is_ok()
+        );
+        assert!(pre_submit(&hooks("[ \"$1\" = main.cpp ]"), &context).is_ok());
+        assert!(pre_submit(&hooks("exit 3"), &context).is_err());
+    }
+
+    #[test]
+    fn accepts_hyphenated_keys() {
+        let hooks: HooksConfig =
+            serde_json::from_str(r#"{ "pre-submit": ["a"], "post_verdict": ["b"] }"#).unwrap();
+        assert_eq!(hooks.pre_submit, vec!["a"]);
+        assert_eq!(hooks.post_verdict, vec!["b"]);
+    }
+}
@@ -0,0 +1,317 @@
+use super::problem;
+use base64::Engine;
+use base64::engine::general_purpose;
+use colored::Colorize;
+use crossterm::terminal;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use reqwest::blocking::Client;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Cursor, IsTerminal, Write};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// How images are shown, `KILO_CLI_IMAGES` (`kitty`, `iterm`, `sixel`, `blocks` or `none`)
+/// overrides the detection.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+    HalfBlocks,
+    Off,
+}
+
+pub(crate) fn protocol() -> Protocol {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    match var("KILO_CLI_IMAGES").as_str() {
+        "kitty" => return Protocol::Kitty,
+        "iterm" => return Protocol::Iterm,
+        "sixel" => return Protocol::Sixel,
+        "blocks" => return Protocol::HalfBlocks,
+        "none" | "off" => return Protocol::Off,
+        _ => {}
+    }
+    if !io::stdout().is_terminal() {
+        return Protocol::Off;
+    }
+
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+        Protocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        Protocol::Iterm
+    } else if term.contains("sixel") || term.starts_with("foot") || term == "mlterm" {
+        Protocol::Sixel
+    } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+        Protocol::HalfBlocks
+    } else {
+        Protocol::Off
+    }
+}
+
+/// Downloads an image referenced by a statement, either a full URL or one of the problem's attachments.
+pub(crate) fn fetch(
+    client: &Client,
+    problem: &str,
+    url: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let response = client.get(url).send()?;
+        if !response.status().is_success() {
+            return Err(format!("Couldn't download {}", url).into());
+        }
+        return Ok(response.bytes()?.to_vec());
+    }
+    problem::attachment(client, problem, file_name(url))
+}
+
+/// `/assets/problem/1/attachment/graph.png` is the `graph.png` attachment.
+pub(crate) fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// An image drawn on top of rows the pager leaves empty.
+pub(crate) struct Graphic {
+    pub rows: u16,
+    cols: u16,
+    encoded: Encoded,
+}
+
+enum Encoded {
+    /// Sent once under `id`, then only placed again.
+    Kitty {
+        id: u32,
+        png: String,
+    },
+    Iterm(String),
+    Sixel(String),
+}
+
+pub(crate) enum Picture {
+    /// Half-block rendering, printed as regular text.
+    Lines(Vec<String>),
+    Graphic(Graphic),
+}
+
+/// Where a graphic goes in a rendered document.
+pub(crate) struct Placement {
+    pub line: usize,
+    pub column: u16,
+    pub graphic: Graphic,
+}
+
+static NEXT_KITTY_ID: AtomicU32 = AtomicU32::new(1);
+
+impl Graphic {
+    pub(crate) fn is_kitty(&self) -> bool {
+        matches!(self.encoded, Encoded::Kitty { .. })
+    }
+
+    /// Draws the graphic at the cursor, `transmitted` tells whether kitty already has the image data.
+    pub(crate) fn draw(&self, out: &mut impl Write, transmitted: bool) -> io::Result<()> {
+        match &self.encoded {
+            Encoded::Kitty { id, .. } if transmitted => write!(
+                out,
+                "\x1b_Ga=p,i={},c={},r={},C=1,q=2\x1b\\",
+                id, self.cols, self.rows
+            ),
+            Encoded::Kitty { id, png } => {
+                let chunks: Vec<&[u8]> = png.as_bytes().chunks(4096).collect();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let more = (i + 1 < chunks.len()) as u8;
+                    if i == 0 {
+                        write!(
+                            out,
+                            "\x1b_Ga=T,f=100,i={},c={},r={},C=1,q=2,m={};",
+                            id, self.cols, self.rows, more
+                        )?;
+                    } else {
+                        write!(out, "\x1b_Gm={};", more)?;
+                    }
+                    out.write_all(chunk)?;
+                    write!(out, "\x1b\\")?;
+                }
+                Ok(())
+            }
+            Encoded::Iterm(escape) | Encoded::Sixel(escape) => out.write_all(escape.as_bytes()),
+        }
+    }
+}
+
+/// Removes the kitty images drawn on the screen, keeping their data for the next placement.
+pub(crate) fn clear_kitty(out: &mut impl Write) -> io::Result<()> {
+    write!(out, "\x1b_Ga=d,d=a,q=2\x1b\\")
+}
+
+/// The size of a terminal cell in pixels, guessed when the terminal doesn't say.
+fn cell_size() -> (u32, u32) {
+    match terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns) as u32,
+            (size.height / size.rows) as u32,
+        ),
+        _ => (8, 16),
+    }
+}
+
+/// Statements are drawn for a white page, so transparent parts become white.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Lays an image out in at most `max_cols` columns and `max_rows` rows, never scaling it up.
+pub(crate) fn picture(
+    data: &[u8],
+    protocol: Protocol,
+    max_cols: usize,
+    max_rows: usize,
+) -> Result<Picture, String> {
+    let image = image::load_from_memory(data).map_err(|e| e.to_string())?;
+    let (cell_width, cell_height) = cell_size();
+    let (width, height) = (image.width().max(1), image.height().max(1));
+
+    let mut cols = width.div_ceil(cell_width).min(max_cols.max(1) as u32);
+    let mut rows = (height * cols * cell_width).div_ceil(width * cell_height);
+    if rows > max_rows.max(1) as u32 {
+        rows = max_rows.max(1) as u32;
+        cols = (width * rows * cell_height / (height * cell_width)).max(1);
+    }
+    let rows = rows.max(1);
+
+    let encoded = match protocol {
+        Protocol::Off => return Err("Images are turned off".into()),
+        Protocol::HalfBlocks => {
+            let pixels = flatten(&image.resize(cols, rows * 2, FilterType::Triangle));
+            return Ok(Picture::Lines(half_blocks(&pixels)));
+        }
+        Protocol::Kitty => {
+            let mut png = Cursor::new(vec![]);
+            image
+                .write_to(&mut png, ImageFormat::Png)
+                .map_err(|e| e.to_string())?;
+            Encoded::Kitty {
+                id: NEXT_KITTY_ID.fetch_add(1, Ordering::Relaxed),
+                png: general_purpose::STANDARD.encode(png.into_inner()),
+            }
+        }
+        Protocol::Iterm => Encoded::Iterm(format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            data.len(),
+            cols,
+            rows,
+            general_purpose::STANDARD.encode(data)
+        )),
+        Protocol::Sixel => {
+            let resized = image.resize(cols * cell_width, rows * cell_height, FilterType::Triangle);
+            Encoded::Sixel(sixel(&flatten(&resized)))
+        }
+    };
+    Ok(Picture::Graphic(Graphic {
+        rows: rows as u16,
+        cols: cols as u16,
+        encoded,
+    }))
+}
+
+/// Two pixels per cell: the upper one as the foreground of `▀`, the lower one as its background.
+fn half_blocks(pixels: &RgbImage) -> Vec<String> {
+    (0..pixels.height())
+        .step_by(2)
+        .map(|y| {
+            (0..pixels.width())
+                .map(|x| {
+                    let [r, g, b] = pixels.get_pixel(x, y).0;
+                    let [br, bg, bb] = if y + 1 < pixels.height() {
+                        pixels.get_pixel(x, y + 1).0
+                    } else {
+                        [r, g, b]
+                    };
+                    "▀".truecolor(r, g, b).on_truecolor(br, bg, bb).to_string()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Encodes the pixels as sixels, with colours reduced to a 6×6×6 cube.
+fn sixel(pixels: &RgbImage) -> String {
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let index = |x: u32, y: u32| {
+        let [r, g, b] = pixels.get_pixel(x, y).0;
+        (level(r) * 36 + level(g) * 6 + level(b)) as usize
+    };
+
+    let (width, height) = pixels.dimensions();
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for colour in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            colour,
+            percent(colour / 36),
+            percent(colour / 6 % 6),
+            percent(colour % 6)
+        ));
+    }
+
+    for top in (0..height).step_by(6) {
+        let mut bands: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for x in 0..width {
+            for dy in 0..6.min(height - top) {
+                let band = bands
+                    .entry(index(x, top + dy))
+                    .or_insert_with(|| vec![0; width as usize]);
+                band[x as usize] |= 1 << dy;
+            }
+        }
+        for (colour, band) in bands {
+            out.push_str(&format!("#{}", colour));
+            let mut x = 0;
+            while x < band.len() {
+                let run = band[x..].iter().take_while(|&&b| b == band[x]).count();
+                let c = (63 + band[x]) as char;
+                if run > 3 {
+                    out.push_str(&format!("!{}{}", run, c));
+                } else {
+                    out.extend(std::iter::repeat_n(c, run));
+                }
+                x += run;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_sixel_bands() {
+        let pixels = RgbImage::from_pixel(4, 7, Rgb([255, 255, 255]));
+        let encoded = sixel(&pixels);
+        assert!(encoded.starts_with("\x1bPq\"1;1;4;7"));
+        // A full band of six rows, then one of a single row, both white (colour 215).
+        assert!(encoded.ends_with("#215!4~$-#215!4@$-\x1b\\"));
+    }
+
+    #[test]
+    fn finds_attachment_names() {
+        assert_eq!(file_name("graph.png"), "graph.png");
+        assert_eq!(
+            file_name("/assets/problem/12/attachment/graph.png?t=1"),
+            "graph.png"
+        );
+    }
+}
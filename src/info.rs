@@ -0,0 +1,223 @@
+use super::problem::{self, Contest, ProblemDetails, Tag};
+use super::waiter;
+use colored::Colorize;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What's known about a problem besides its statement.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Info {
+    pub details: ProblemDetails,
+    pub tags: Vec<Tag>,
+    /// `None` when not logged in or never submitted.
+    pub best_score: Option<f64>,
+    /// The running contests the problem is part of, never archived since it goes stale.
+    #[serde(skip)]
+    pub running: Vec<String>,
+}
+
+/// Fetches the problem's details, the optional parts are left empty when they can't be fetched.
+pub(crate) fn fetch(client: &Client, id: &str) -> Result<Info, Box<dyn std::error::Error>> {
+    let details = problem::details(client, id)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    Ok(Info {
+        details,
+        tags: problem::tags(client, id).unwrap_or_default(),
+        best_score: problem::best_score(client, id).ok().flatten(),
+        running: problem::contests(client, id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|contest| is_running(contest, now))
+            .map(|contest| contest.name)
+            .collect(),
+    })
+}
+
+/// Seconds since the epoch of an RFC 3339 time such as `2025-03-01T10:00:00+02:00`.
+fn unix_time(time: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| time.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // Days since the epoch, from the civil calendar.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let rest = time[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset = match rest.chars().next() {
+        Some('Z' | 'z') | None => 0,
+        Some(sign @ ('+' | '-')) => {
+            let hours: i64 = rest.get(1..3)?.parse().ok()?;
+            let minutes: i64 = rest.get(4..6)?.parse().ok()?;
+            let offset = hours * 3600 + minutes * 60;
+            if sign == '+' { offset } else { -offset }
+        }
+        Some(_) => return None,
+    };
+    Some(days * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
+
+fn is_running(contest: &Contest, now: i64) -> bool {
+    match (unix_time(&contest.start_time), unix_time(&contest.end_time)) {
+        (Some(start), Some(end)) => start <= now && now < end,
+        _ => false,
+    }
+}
+
+fn format_score(score: f64) -> String {
+    format!("{}", (score * 100.0).round() / 100.0)
+}
+
+/// The header lines, as labels and values.
+pub(crate) fn fields(info: &Info) -> Vec<(&'static str, String)> {
+    let details = &info.details;
+    let mut fields = vec![
+        ("Problem", format!("{} (#{})", details.name, details.id)),
+        ("Time limit", format!("{}s", details.time_limit)),
+        (
+            "Memory limit",
+            format!("{} MB", details.memory_limit / 1024),
+        ),
+        (
+            "Input/output",
+            if details.console_input {
+                "standard input/output".to_string()
+            } else {
+                format!("{0}.in / {0}.out", details.test_name)
+            },
+        ),
+    ];
+
+    let tags_of = |kind: &str| {
+        info.tags
+            .iter()
+            .filter(|tag| tag.kind == kind)
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<_>>()
+    };
+    let mut source = tags_of("contest");
+    if !details.source_credits.is_empty() {
+        source.insert(0, details.source_credits.as_str());
+    }
+    if !source.is_empty() {
+        fields.push(("Source", source.join(", ")));
+    }
+    let authors = tags_of("author");
+    if !authors.is_empty() {
+        fields.push(("Author", authors.join(", ")));
+    }
+    // Everything that isn't an author or a source, e.g. the methods.
+    let others: Vec<&str> = info
+        .tags
+        .iter()
+        .filter(|tag| tag.kind != "author" && tag.kind != "contest")
+        .map(|tag| tag.name.as_str())
+        .collect();
+    if !others.is_empty() {
+        fields.push(("Tags", others.join(", ")));
+    }
+
+    fields.push(("Max score", format_score(details.score_scale)));
+    fields.push((
+        "Your score",
+        info.best_score
+            .map_or("not attempted".to_string(), format_score),
+    ));
+    if !info.running.is_empty() {
+        fields.push(("Contest", format!("running: {}", info.running.join(", "))));
+    }
+    fields
+}
+
+/// The header shown above the statement in the pager.
+pub(crate) fn markdown(info: &Info) -> String {
+    let mut header: String = fields(info)
+        .iter()
+        .map(|(label, value)| format!("**{}:** {}  \n", label, value.replace('*', "\\*")))
+        .collect();
+    header.push_str("\n---\n\n");
+    header
+}
+
+pub fn info(id: &str) {
+    let spinner = waiter::Waiter::start();
+    let info = fetch(&Client::new(), id);
+    spinner.stop();
+
+    match info {
+        Ok(info) => {
+            for (label, value) in fields(&info) {
+                println!("{:>12}: {}", label.bold(), value);
+            }
+        }
+        Err(e) => println!("{} {}", "Couldn't get the problem:".red(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_contest_times() {
+        assert_eq!(unix_time("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(unix_time("2024-03-01T12:30:00Z"), Some(1709296200));
+        assert_eq!(
+            unix_time("2024-03-01T14:30:00.123456+02:00"),
+            Some(1709296200)
+        );
+        assert_eq!(unix_time("soon"), None);
+    }
+
+    #[test]
+    fn sorts_tags_into_fields() {
+        let tag = |name: &str, kind: &str| Tag {
+            id: 0,
+            name: name.into(),
+            kind: kind.into(),
+        };
+        let info = Info {
+            details: ProblemDetails {
+                id: 12,
+                name: "sum".into(),
+                time_limit: 0.5,
+                memory_limit: 65536,
+                test_name: "sum".into(),
+                score_scale: 100.0,
+                ..Default::default()
+            },
+            tags: vec![
+                tag("ONI 2024", "contest"),
+                tag("Ana", "author"),
+                tag("dp", "method"),
+            ],
+            best_score: None,
+            running: vec![],
+        };
+        let fields = fields(&info);
+        let field = |label: &str| {
+            fields
+                .iter()
+                .find(|(l, _)| *l == label)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(field("Memory limit"), Some("64 MB"));
+        assert_eq!(field("Input/output"), Some("sum.in / sum.out"));
+        assert_eq!(field("Source"), Some("ONI 2024"));
+        assert_eq!(field("Author"), Some("Ana"));
+        assert_eq!(field("Tags"), Some("dp"));
+        assert_eq!(field("Your score"), Some("not attempted"));
+        assert_eq!(field("Contest"), None);
+    }
+}
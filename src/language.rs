@@ -17,7 +17,7 @@ pub fn set_language(language: &str) {
 
     if ALLOWED.contains(&language) {
         let res = credential_manager::CredentialManager::global()
-            .set::<credential_manager::Language>(&language);
+            .set::<credential_manager::Language>(language);
         match res {
             Ok(()) => println!("Language set to {}", language),
             Err(e) => println!("Language not set, error: {}", e),
@@ -0,0 +1,488 @@
+/// Turns the math of a statement (`1 \leq N \leq 10^5`) into Unicode (`1 ≤ N ≤ 10⁵`).
+/// Returns `None` on anything it doesn't understand, so the caller can show the source instead.
+pub(crate) fn to_unicode(source: &str) -> Option<String> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+    };
+    let output = parser.expression(false)?;
+    if parser.pos < parser.chars.len() {
+        return None;
+    }
+    Some(output.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Functions like `\log`, printed as their name.
+const OPERATORS: &[&str] = &[
+    "log", "ln", "lg", "sin", "cos", "tan", "exp", "max", "min", "gcd", "lim", "sup", "inf", "det",
+    "deg", "dim", "arg",
+];
+
+fn symbol(name: &str) -> Option<&'static str> {
+    if let Some(operator) = OPERATORS.iter().find(|&&operator| operator == name) {
+        return Some(operator);
+    }
+    let symbol = match name {
+        "leq" | "le" | "leqslant" => "≤",
+        "geq" | "ge" | "geqslant" => "≥",
+        "neq" | "ne" => "≠",
+        "lt" => "<",
+        "gt" => ">",
+        "ll" => "≪",
+        "gg" => "≫",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "cdot" | "cdotp" => "·",
+        "times" => "×",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "•",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "ldots" | "dots" | "dotsc" | "dotsb" => "…",
+        "cdots" => "⋯",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "emptyset" | "varnothing" => "∅",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "mid" => "∣",
+        "vert" | "lvert" | "rvert" => "|",
+        "Vert" | "lVert" | "rVert" | "parallel" => "∥",
+        "perp" => "⊥",
+        "angle" => "∠",
+        "triangle" => "△",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "prime" => "′",
+        "dagger" => "†",
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" | "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" | "vartheta" => "θ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" | "varrho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" | "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "mod" | "bmod" => " mod ",
+        "quad" => "  ",
+        "qquad" => "    ",
+        "," | ";" | ":" | " " | "\\" => " ",
+        "!" | "big" | "Big" | "bigg" | "Bigg" | "bigl" | "bigr" | "Bigl" | "Bigr" | "biggl"
+        | "biggr" | "limits" | "nolimits" | "displaystyle" | "textstyle" => "",
+        "{" => "{",
+        "}" => "}",
+        "%" => "%",
+        "$" => "$",
+        "#" => "#",
+        "&" => "&",
+        "_" => "_",
+        "|" => "∥",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn superscript(c: char) -> Option<char> {
+    let superscript = match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'f' => 'ᶠ',
+        'g' => 'ᵍ',
+        'h' => 'ʰ',
+        'i' => 'ⁱ',
+        'j' => 'ʲ',
+        'k' => 'ᵏ',
+        'l' => 'ˡ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        'r' => 'ʳ',
+        's' => 'ˢ',
+        't' => 'ᵗ',
+        'u' => 'ᵘ',
+        'v' => 'ᵛ',
+        'w' => 'ʷ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'z' => 'ᶻ',
+        'T' => 'ᵀ',
+        _ => return None,
+    };
+    Some(superscript)
+}
+
+fn subscript(c: char) -> Option<char> {
+    let subscript = match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        _ => return None,
+    };
+    Some(subscript)
+}
+
+/// Wraps anything longer than a number or a name in parentheses, e.g. the parts of a fraction.
+fn grouped(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().all(char::is_alphanumeric) {
+        text.to_string()
+    } else {
+        format!("({})", text)
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Everything up to the end of the input, or the `}` closing the current group.
+    fn expression(&mut self, in_group: bool) -> Option<String> {
+        let mut output = String::new();
+        while let Some(c) = self.peek() {
+            match c {
+                '}' if in_group => return Some(output),
+                '}' | '&' => return None,
+                '^' | '_' => {
+                    self.pos += 1;
+                    let argument = self.argument()?;
+                    output.push_str(&script(&argument, c == '^'));
+                }
+                _ => output.push_str(&self.atom()?),
+            }
+        }
+        if in_group { None } else { Some(output) }
+    }
+
+    fn group(&mut self) -> Option<String> {
+        self.pos += 1;
+        let content = self.expression(true)?;
+        self.pos += 1;
+        Some(content)
+    }
+
+    /// What a command or a script applies to: a group, a command or a single character.
+    fn argument(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        match self.peek()? {
+            '{' => self.group(),
+            '}' | '^' | '_' => None,
+            _ => self.atom(),
+        }
+    }
+
+    /// The content of `\text{...}`, taken literally.
+    fn text_argument(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        if self.peek()? != '{' {
+            return None;
+        }
+        self.pos += 1;
+        let mut depth = 0;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Some(text),
+                '}' => depth -= 1,
+                '$' => return None,
+                _ => text.push(c),
+            }
+        }
+        None
+    }
+
+    fn atom(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        match c {
+            '{' => self.group(),
+            '\\' => {
+                self.pos += 1;
+                self.command()
+            }
+            '~' => {
+                self.pos += 1;
+                Some(" ".to_string())
+            }
+            '\'' => {
+                self.pos += 1;
+                Some("′".to_string())
+            }
+            c if c.is_whitespace() => {
+                self.skip_whitespace();
+                Some(" ".to_string())
+            }
+            _ => {
+                self.pos += 1;
+                Some(c.to_string())
+            }
+        }
+    }
+
+    fn command(&mut self) -> Option<String> {
+        let first = self.peek()?;
+        self.pos += 1;
+        let mut name = first.to_string();
+        if first.is_ascii_alphabetic() {
+            while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+                name.push(c);
+                self.pos += 1;
+            }
+        }
+
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                Some(format!("{}/{}", grouped(&numerator), grouped(&denominator)))
+            }
+            "binom" => {
+                let n = self.argument()?;
+                let k = self.argument()?;
+                Some(format!("C({}, {})", n.trim(), k.trim()))
+            }
+            "sqrt" => {
+                self.skip_whitespace();
+                let root = if self.peek() == Some('[') {
+                    let end = self.chars[self.pos..].iter().position(|&c| c == ']')?;
+                    let index: String = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                    self.pos += end + 1;
+                    match index.trim() {
+                        "2" => "√".to_string(),
+                        "3" => "∛".to_string(),
+                        "4" => "∜".to_string(),
+                        index => format!(
+                            "{}√",
+                            index.chars().map(superscript).collect::<Option<String>>()?
+                        ),
+                    }
+                } else {
+                    "√".to_string()
+                };
+                let radicand = self.argument()?;
+                Some(format!("{}{}", root, grouped(&radicand)))
+            }
+            "text" | "textrm" | "textit" | "textbf" | "texttt" | "mbox" | "mathrm" | "mathit"
+            | "mathbf" | "mathtt" | "mathsf" | "operatorname" => {
+                let text = self.text_argument()?;
+                Some(text)
+            }
+            "mathbb" => {
+                let letter = self.text_argument()?;
+                let blackboard = match letter.trim() {
+                    "N" => "ℕ",
+                    "Z" => "ℤ",
+                    "Q" => "ℚ",
+                    "R" => "ℝ",
+                    "C" => "ℂ",
+                    _ => return None,
+                };
+                Some(blackboard.to_string())
+            }
+            "pmod" => {
+                let modulus = self.argument()?;
+                Some(format!(" (mod {})", modulus.trim()))
+            }
+            "overline" | "bar" => {
+                let content = self.argument()?;
+                Some(content.chars().flat_map(|c| [c, '\u{305}']).collect())
+            }
+            "not" => {
+                let negated = self.argument()?;
+                match negated.as_str() {
+                    "=" => Some("≠".to_string()),
+                    "∈" => Some("∉".to_string()),
+                    "⊂" => Some("⊄".to_string()),
+                    _ => None,
+                }
+            }
+            "left" | "right" => {
+                self.skip_whitespace();
+                if self.peek() == Some('.') {
+                    self.pos += 1;
+                }
+                Some(String::new())
+            }
+            name => symbol(name).map(str::to_string),
+        }
+    }
+}
+
+/// Applies `^` or `_` to an argument, falling back to `^(...)` when some character has no
+/// Unicode superscript or subscript.
+fn script(argument: &str, sup: bool) -> String {
+    let argument: String = argument.chars().filter(|c| !c.is_whitespace()).collect();
+    if sup {
+        match argument.as_str() {
+            "′" | "″" => return argument,
+            "∘" => return "°".to_string(),
+            _ => {}
+        }
+    }
+    let map = if sup { superscript } else { subscript };
+    match argument.chars().map(map).collect::<Option<String>>() {
+        Some(mapped) => mapped,
+        None => {
+            let marker = if sup { '^' } else { '_' };
+            if argument.chars().count() == 1 {
+                format!("{}{}", marker, argument)
+            } else {
+                format!("{}({})", marker, argument)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_common_math() {
+        assert_eq!(
+            to_unicode(r"1 \leq N \leq 10^5").as_deref(),
+            Some("1 ≤ N ≤ 10⁵")
+        );
+        assert_eq!(
+            to_unicode(r"\sum_{i=1}^{n} a_i \cdot 2^{-k}").as_deref(),
+            Some("∑ᵢ₌₁ⁿ aᵢ · 2⁻ᵏ")
+        );
+        assert_eq!(
+            to_unicode(r"\frac{n(n+1)}{2} \neq \sqrt{x+1}, \alpha").as_deref(),
+            Some("(n(n+1))/2 ≠ √(x+1), α")
+        );
+        assert_eq!(to_unicode(r"a_{i,j}").as_deref(), Some("a_(i,j)"));
+        assert_eq!(to_unicode(r"\text{dacă } x").as_deref(), Some("dacă x"));
+    }
+
+    #[test]
+    fn rejects_unsupported_constructs() {
+        assert_eq!(to_unicode(r"\begin{matrix} 1 & 2 \end{matrix}"), None);
+        assert_eq!(to_unicode(r"\hat{x}"), None);
+        assert_eq!(to_unicode(r"\frac{1}{2"), None);
+    }
+}
@@ -45,8 +45,8 @@ fn login_and_print(username: String, password: String) {
     let response = match response {
         Ok(response) => response,
         Err(e) => {
-            println!("Ran into error: {}", e.to_string());
-            return ();
+            println!("Ran into error: {}", e);
+            return;
         }
     };
 
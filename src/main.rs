@@ -1,4 +1,4 @@
-use crate::cli::{Cli, Commands};
+use crate::cli::{Cli, Commands, IndexAction, QueueAction};
 use clap::Parser;
 use reqwest::blocking;
 
@@ -8,13 +8,37 @@ mod logging;
 mod waiter;
 
 mod checker;
+mod checks;
+mod config;
+mod diff;
+mod filters;
+mod history;
+mod hooks;
+mod runner;
 
+mod archive;
 mod browser;
+mod bundle;
+mod catalogue;
+mod images;
+mod info;
 mod language;
+mod latex;
+mod markdown;
+mod output_only;
+mod pager;
+mod problem;
+mod queue;
+mod rust_bundle;
 mod statement;
+mod storage;
+mod submission_index;
 mod submitter;
+#[cfg(test)]
+mod test_dir;
 mod user_info;
 mod view;
+mod watch;
 
 fn main() {
     let cli = Cli::parse();
@@ -29,9 +53,34 @@ fn main() {
         }
 
         Commands::Me => user_info::get(),
-        Commands::Search { name } => browser::search(&name),
-        Commands::Submit { path } => {
-            submitter::submit(path);
+        Commands::Search {
+            name,
+            offline: true,
+            ..
+        } => browser::search_offline(&name),
+        Commands::Search { name, filters, .. } => browser::search(&name, &filters),
+        Commands::Submit {
+            path,
+            bundle,
+            include_dirs,
+            libraries,
+            force,
+            skip_checks,
+            queue,
+            problem,
+        } => {
+            submitter::submit(
+                path,
+                submitter::SubmitOptions {
+                    bundle,
+                    include_dirs,
+                    libraries,
+                    force,
+                    skip_checks,
+                    queue,
+                    problem,
+                },
+            );
         }
         Commands::SetLanguage { name } => {
             language::set_language(name.as_ref());
@@ -44,8 +93,51 @@ fn main() {
             statement::set_language(name.as_ref());
         }
 
-        Commands::View => {
-            view::view_latest_statement();
+        Commands::Info { id } => {
+            info::info(&id);
         }
+
+        Commands::View { problem, both } => {
+            view::view_latest_statement(problem.as_deref(), both);
+        }
+
+        Commands::Test {
+            path,
+            tests,
+            time_limit,
+            interactor,
+            watch,
+            submit_on_pass,
+        } => {
+            runner::test(path, tests, time_limit, interactor, watch, submit_on_pass);
+        }
+
+        Commands::Download { dir } => {
+            problem::download(dir);
+        }
+
+        Commands::Bundle {
+            path,
+            output,
+            include_dirs,
+            libraries,
+        } => {
+            bundle::bundle(path, output, include_dirs, libraries);
+        }
+
+        Commands::Index { action } => match action {
+            IndexAction::Sync => catalogue::sync(),
+        },
+
+        Commands::Mirror { selection } => archive::mirror(&selection),
+
+        Commands::Grep { pattern } => archive::grep(&pattern),
+
+        Commands::Recent => history::recent(),
+
+        Commands::Queue { action } => match action {
+            QueueAction::Flush => queue::flush(),
+            QueueAction::List => queue::list(),
+        },
     }
 }
@@ -0,0 +1,721 @@
+use super::images::{self, Picture, Placement};
+use super::latex;
+#[cfg(test)]
+use super::pager;
+use colored::Colorize;
+use pulldown_cmark::{Alignment, Event, Options, Parser, Tag, TagEnd};
+use std::mem;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    strikethrough: bool,
+    code: bool,
+    link: bool,
+    dim: bool,
+    heading: u8,
+}
+
+#[derive(Clone, Debug)]
+struct Span {
+    text: String,
+    style: Style,
+}
+
+/// A rendered line, along with how many columns it takes once the escape codes are gone.
+#[derive(Default, Clone, Debug)]
+struct Line {
+    text: String,
+    width: usize,
+}
+
+impl Line {
+    fn push(&mut self, text: &str, style: Style) {
+        self.text.push_str(&paint(text, style));
+        self.width += text.width();
+    }
+}
+
+fn paint(text: &str, style: Style) -> String {
+    if style == Style::default() {
+        return text.to_string();
+    }
+    let mut painted = text.normal();
+    if style.heading > 0 {
+        painted = painted.bright_cyan().bold();
+        if style.heading == 1 {
+            painted = painted.underline();
+        }
+    }
+    if style.bold {
+        painted = painted.bold();
+    }
+    if style.italic {
+        painted = painted.italic();
+    }
+    if style.strikethrough {
+        painted = painted.strikethrough();
+    }
+    if style.code {
+        painted = painted.bright_yellow();
+    }
+    if style.link {
+        painted = painted.blue().underline();
+    }
+    if style.dim {
+        painted = painted.bright_black();
+    }
+    painted.to_string()
+}
+
+enum Token {
+    Word(Vec<Span>),
+    Break,
+}
+
+fn tokenize(spans: &[Span]) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut word: Vec<Span> = vec![];
+    for span in spans {
+        for c in span.text.chars() {
+            if c == '\n' || c.is_whitespace() {
+                if !word.is_empty() {
+                    tokens.push(Token::Word(mem::take(&mut word)));
+                }
+                if c == '\n' {
+                    tokens.push(Token::Break);
+                }
+                continue;
+            }
+            match word.last_mut() {
+                Some(piece) if piece.style == span.style => piece.text.push(c),
+                _ => word.push(Span {
+                    text: c.to_string(),
+                    style: span.style,
+                }),
+            }
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(Token::Word(word));
+    }
+    tokens
+}
+
+/// Word-wraps styled text to `width` columns, splitting words that don't fit on a line of their own.
+fn wrap(spans: &[Span], width: usize) -> Vec<Line> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut line = Line::default();
+    for token in tokenize(spans) {
+        let word = match token {
+            Token::Break => {
+                lines.push(mem::take(&mut line));
+                continue;
+            }
+            Token::Word(word) => word,
+        };
+        let word_width: usize = word.iter().map(|piece| piece.text.width()).sum();
+        if line.width > 0 && line.width + 1 + word_width > width {
+            lines.push(mem::take(&mut line));
+        }
+        if line.width > 0 {
+            line.push(" ", Style::default());
+        }
+        for piece in word {
+            for c in piece.text.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if line.width > 0 && line.width + char_width > width {
+                    lines.push(mem::take(&mut line));
+                }
+                line.push(c.encode_utf8(&mut [0; 4]), piece.style);
+            }
+        }
+    }
+    if line.width > 0 || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn pad(line: &Line, width: usize, alignment: Alignment) -> String {
+    let space = width.saturating_sub(line.width);
+    let (left, right) = match alignment {
+        Alignment::Right => (space, 0),
+        Alignment::Center => (space / 2, space - space / 2),
+        Alignment::Left | Alignment::None => (0, space),
+    };
+    format!("{}{}{}", " ".repeat(left), line.text, " ".repeat(right))
+}
+
+fn border(text: String) -> String {
+    text.bright_black().to_string()
+}
+
+#[derive(Default)]
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span>>>,
+    in_head: bool,
+}
+
+/// A rendered statement: its lines, plus the images to draw over some of them.
+pub(crate) struct Document {
+    pub lines: Vec<String>,
+    pub placements: Vec<Placement>,
+    /// The line and title of every heading.
+    pub headings: Vec<(usize, String)>,
+}
+
+/// Fetches and lays out the image at a URL in at most the given number of columns.
+pub(crate) type ImageLoader<'a> = &'a mut dyn FnMut(&str, usize) -> Option<Picture>;
+
+struct Renderer<'a> {
+    width: usize,
+    lines: Vec<String>,
+    placements: Vec<Placement>,
+    headings: Vec<(usize, String)>,
+    load_image: ImageLoader<'a>,
+    style: Style,
+    spans: Vec<Span>,
+    /// The next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+    item_marker: Option<String>,
+    quote_depth: usize,
+    code_block: Option<String>,
+    table: Option<Table>,
+    image: Option<(String, String)>,
+}
+
+impl Renderer<'_> {
+    fn text(&mut self, text: &str) {
+        if let Some(code) = &mut self.code_block {
+            code.push_str(text);
+        } else if let Some((_, alt)) = &mut self.image {
+            alt.push_str(text);
+        } else {
+            self.spans.push(Span {
+                text: text.to_string(),
+                style: self.style,
+            });
+        }
+    }
+
+    fn styled(&mut self, text: &str, style: Style) {
+        self.spans.push(Span {
+            text: text.to_string(),
+            style,
+        });
+    }
+
+    /// Separates blocks with a single empty line.
+    fn blank(&mut self) {
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    /// The quote bars and list indentation in front of every line of the current block.
+    fn indent(&self) -> (String, usize) {
+        let quote = "│ ".repeat(self.quote_depth);
+        let list = "  ".repeat(self.lists.len());
+        let width = quote.width() + list.width();
+        (format!("{}{}", border(quote), list), width)
+    }
+
+    fn emit(&mut self, line: String) {
+        let (indent, _) = self.indent();
+        self.lines.push(format!("{}{}", indent, line));
+    }
+
+    fn flush(&mut self) {
+        let marker = self.item_marker.take();
+        if self.spans.is_empty() && marker.is_none() {
+            return;
+        }
+        let spans = mem::take(&mut self.spans);
+        let (indent, indent_width) = self.indent();
+        // The marker hangs in the indentation of the item's own list.
+        let (indent, indent_width) = match &marker {
+            Some(_) => (
+                indent.strip_suffix("  ").unwrap_or(&indent).to_string(),
+                indent_width.saturating_sub(2),
+            ),
+            None => (indent, indent_width),
+        };
+        let marker_width = marker.as_ref().map_or(0, |m| m.width());
+        let available = self
+            .width
+            .saturating_sub(indent_width + marker_width)
+            .max(10);
+        for (i, line) in wrap(&spans, available).into_iter().enumerate() {
+            let lead = match (&marker, i) {
+                (Some(marker), 0) => marker.clone(),
+                _ => " ".repeat(marker_width),
+            };
+            self.lines.push(format!("{}{}{}", indent, lead, line.text));
+        }
+    }
+
+    fn code_block(&mut self, code: &str) {
+        let (_, indent_width) = self.indent();
+        let max_inner = self.width.saturating_sub(indent_width + 4).max(10);
+        let plain = Style::default();
+        let mut rows = vec![];
+        for line in code.lines() {
+            // Keep the spaces of code, only split what doesn't fit.
+            let mut row = Line::default();
+            for c in line.chars() {
+                if row.width > 0 && row.width + c.width().unwrap_or(0) > max_inner {
+                    rows.push(mem::take(&mut row));
+                }
+                row.push(c.encode_utf8(&mut [0; 4]), plain);
+            }
+            rows.push(row);
+        }
+        let inner = rows.iter().map(|row| row.width).max().unwrap_or(0).max(1);
+
+        self.blank();
+        self.emit(border(format!("┌{}┐", "─".repeat(inner + 2))));
+        for row in &rows {
+            self.emit(format!(
+                "{} {} {}",
+                border("│".into()),
+                pad(row, inner, Alignment::Left),
+                border("│".into())
+            ));
+        }
+        self.emit(border(format!("└{}┘", "─".repeat(inner + 2))));
+        self.blank();
+    }
+
+    fn table(&mut self, table: Table) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let (_, indent_width) = self.indent();
+        let available = self
+            .width
+            .saturating_sub(indent_width + 3 * columns + 1)
+            .max(columns * 3);
+
+        let mut widths = vec![1; columns];
+        for row in &table.rows {
+            for (column, cell) in row.iter().enumerate() {
+                let natural = wrap(cell, usize::MAX).iter().map(|l| l.width).max();
+                widths[column] = widths[column].max(natural.unwrap_or(0));
+            }
+        }
+        while widths.iter().sum::<usize>() > available {
+            let widest = (0..columns).max_by_key(|&c| widths[c]).unwrap();
+            if widths[widest] <= 3 {
+                break;
+            }
+            widths[widest] -= 1;
+        }
+
+        let rule = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            border(format!("{}{}{}", left, segments.join(middle), right))
+        };
+
+        self.blank();
+        self.emit(rule("┌", "┬", "┐"));
+        for (r, row) in table.rows.iter().enumerate() {
+            let cells: Vec<Vec<Line>> = (0..columns)
+                .map(|c| {
+                    row.get(c)
+                        .map_or(vec![Line::default()], |cell| wrap(cell, widths[c]))
+                })
+                .collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+            for i in 0..height {
+                let parts: Vec<String> = (0..columns)
+                    .map(|c| {
+                        let alignment = table.alignments.get(c).copied().unwrap_or(Alignment::None);
+                        let line = cells[c].get(i).cloned().unwrap_or_default();
+                        pad(&line, widths[c], alignment)
+                    })
+                    .collect();
+                let separator = format!(" {} ", border("│".into()));
+                self.emit(format!(
+                    "{} {} {}",
+                    border("│".into()),
+                    parts.join(&separator),
+                    border("│".into())
+                ));
+            }
+            if r == 0 && table.rows.len() > 1 {
+                self.emit(rule("├", "┼", "┤"));
+            }
+        }
+        self.emit(rule("└", "┴", "┘"));
+        self.blank();
+    }
+
+    /// Shows an image as its own block, or a placeholder in the text when it can't be shown.
+    fn image(&mut self, url: &str, alt: &str) {
+        let name = images::file_name(url);
+        let (_, indent_width) = self.indent();
+        match (self.load_image)(url, self.width.saturating_sub(indent_width)) {
+            Some(Picture::Lines(lines)) => {
+                self.flush();
+                lines.into_iter().for_each(|line| self.emit(line));
+            }
+            Some(Picture::Graphic(graphic)) => {
+                self.flush();
+                let line = self.lines.len();
+                // Shown until the image is drawn over it.
+                self.emit(border(format!("[image: {}]", name)));
+                for _ in 1..graphic.rows {
+                    self.emit(" ".to_string());
+                }
+                self.placements.push(Placement {
+                    line,
+                    column: indent_width as u16,
+                    graphic,
+                });
+            }
+            None => {
+                let label = if alt.is_empty() { name } else { alt };
+                let style = Style {
+                    dim: true,
+                    ..self.style
+                };
+                self.styled(&format!("[image: {}, `download` saves it]", label), style);
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.blank();
+                self.style.heading = level as u8;
+            }
+            Tag::Paragraph => {}
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.code_block = Some(String::new());
+            }
+            Tag::List(start) => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let depth = self.lists.len();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ if depth.is_multiple_of(2) => "◦ ".to_string(),
+                    _ => "• ".to_string(),
+                };
+                self.item_marker = Some(marker);
+            }
+            Tag::Table(alignments) => {
+                self.flush();
+                self.table = Some(Table {
+                    alignments,
+                    ..Default::default()
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.in_head = matches!(tag, Tag::TableHead);
+                    table.rows.push(vec![]);
+                }
+            }
+            Tag::Emphasis => self.style.italic = true,
+            Tag::Strong => self.style.bold = true,
+            Tag::Strikethrough => self.style.strikethrough = true,
+            Tag::Link { .. } => self.style.link = true,
+            Tag::Image { dest_url, .. } => self.image = Some((dest_url.to_string(), String::new())),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                let title: String = self.spans.iter().map(|span| span.text.as_str()).collect();
+                self.headings.push((self.lines.len(), title));
+                self.flush();
+                self.style.heading = 0;
+                self.blank();
+            }
+            TagEnd::Paragraph => {
+                self.flush();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.blank();
+            }
+            TagEnd::CodeBlock => {
+                let code = self.code_block.take().unwrap_or_default();
+                self.code_block(&code);
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::Item => self.flush(),
+            TagEnd::TableCell => {
+                let mut cell = mem::take(&mut self.spans);
+                if let Some(table) = &mut self.table {
+                    if table.in_head {
+                        cell.iter_mut().for_each(|span| span.style.bold = true);
+                    }
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.table(table);
+                }
+            }
+            TagEnd::Emphasis => self.style.italic = false,
+            TagEnd::Strong => self.style.bold = false,
+            TagEnd::Strikethrough => self.style.strikethrough = false,
+            TagEnd::Link => self.style.link = false,
+            TagEnd::Image => {
+                if let Some((url, alt)) = self.image.take() {
+                    self.image(&url, &alt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text),
+            Event::Code(code) => {
+                let style = Style {
+                    code: true,
+                    ..self.style
+                };
+                self.styled(&code, style);
+            }
+            Event::InlineMath(math) => {
+                let text = latex::to_unicode(&math).unwrap_or_else(|| format!("${}$", math));
+                self.text(&text);
+            }
+            Event::DisplayMath(math) => {
+                let text = latex::to_unicode(&math).unwrap_or_else(|| format!("$${}$$", math));
+                self.text(&text);
+            }
+            Event::SoftBreak => self.text(" "),
+            Event::HardBreak => self.text("\n"),
+            Event::Rule => {
+                self.flush();
+                self.blank();
+                let (_, indent_width) = self.indent();
+                self.emit(border("─".repeat(self.width.saturating_sub(indent_width))));
+                self.blank();
+            }
+            Event::InlineHtml(html) if html.trim_start().starts_with("<br") => self.text("\n"),
+            Event::Html(html) | Event::InlineHtml(html) => {
+                let text = strip_tags(&html);
+                if !text.trim().is_empty() {
+                    self.text(&text);
+                }
+            }
+            Event::TaskListMarker(done) => self.text(if done { "[x] " } else { "[ ] " }),
+            _ => {}
+        }
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Kilonova's `~[graph.png]` (optionally `~[graph.png|width=50%]`) shows an attachment,
+/// rewritten as a regular markdown image outside of code blocks.
+fn attachment_images(source: &str) -> String {
+    let mut output = String::new();
+    let mut fence: Option<&str> = None;
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker => fence = None,
+            _ => {}
+        }
+        if fence.is_some() || marker.is_some() {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find("~[") {
+            let Some(end) = rest[start..].find(']') else {
+                break;
+            };
+            let name = rest[start + 2..start + end]
+                .split('|')
+                .next()
+                .unwrap_or("")
+                .trim();
+            output.push_str(&rest[..start]);
+            output.push_str(&format!("![{}]({})", name, name.replace(' ', "%20")));
+            rest = &rest[start + end + 1..];
+        }
+        output.push_str(rest);
+        output.push('\n');
+    }
+    output
+}
+
+/// Renders a markdown statement as terminal lines at most `width` columns wide.
+pub(crate) fn render(source: &str, width: usize, load_image: ImageLoader) -> Document {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_MATH;
+    let mut renderer = Renderer {
+        width: width.max(20),
+        lines: vec![],
+        placements: vec![],
+        headings: vec![],
+        load_image,
+        style: Style::default(),
+        spans: vec![],
+        lists: vec![],
+        item_marker: None,
+        quote_depth: 0,
+        code_block: None,
+        table: None,
+        image: None,
+    };
+    for event in Parser::new_ext(&attachment_images(source), options) {
+        renderer.event(event);
+    }
+    renderer.flush();
+    while renderer.lines.last().is_some_and(String::is_empty) {
+        renderer.lines.pop();
+    }
+    Document {
+        lines: renderer.lines,
+        placements: renderer.placements,
+        headings: renderer.headings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(lines: Vec<String>) -> Vec<String> {
+        lines.iter().map(|line| pager::strip_ansi(line)).collect()
+    }
+
+    #[test]
+    fn wraps_paragraphs_and_lists() {
+        let lines = plain(
+            render(
+                "# Title\n\nSome **bold** words that wrap.\n\n- first item\n- second",
+                20,
+                &mut |_, _| None,
+            )
+            .lines,
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "Title",
+                "",
+                "Some bold words that",
+                "wrap.",
+                "",
+                "• first item",
+                "• second",
+            ]
+        );
+    }
+
+    #[test]
+    fn boxes_code_and_aligns_tables() {
+        let lines = plain(
+            render(
+                "~~~\n3\n1 2 3\n~~~\n\n| n | answer |\n|---|---:|\n| 1 | 10 |\n",
+                40,
+                &mut |_, _| None,
+            )
+            .lines,
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "┌───────┐",
+                "│ 3     │",
+                "│ 1 2 3 │",
+                "└───────┘",
+                "",
+                "┌───┬────────┐",
+                "│ n │ answer │",
+                "├───┼────────┤",
+                "│ 1 │     10 │",
+                "└───┴────────┘",
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_attachment_images() {
+        let mut requested = vec![];
+        let lines = plain(
+            render(
+                "See ~[graph.png|width=50%].\n\n~~~\n~[kept.png]\n~~~",
+                40,
+                &mut |url, _| {
+                    requested.push(url.to_string());
+                    Some(Picture::Lines(vec!["▀▀".to_string()]))
+                },
+            )
+            .lines,
+        );
+        assert_eq!(requested, vec!["graph.png"]);
+        assert_eq!(lines[..3], ["See", "▀▀", "."]);
+        assert!(lines.contains(&"│ ~[kept.png] │".to_string()));
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Collects the `NN.out` files in `dir`, keyed by test number.
+pub(crate) fn collect(dir: &Path) -> Result<BTreeMap<u32, PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        format!(
+            "Couldn't read the outputs directory {}: {}",
+            dir.display(),
+            e
+        )
+    })?;
+
+    let mut outputs = BTreeMap::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.extension().is_none_or(|ext| ext != "out") {
+            continue;
+        }
+        let Some(test) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if let Some(previous) = outputs.insert(test, path.clone()) {
+            return Err(format!(
+                "Both {} and {} are outputs for test {}",
+                previous.display(),
+                path.display(),
+                test
+            ));
+        }
+    }
+    Ok(outputs)
+}
+
+/// Checks the outputs against the problem's test numbers, reporting missing and extra files.
+pub(crate) fn validate(outputs: &BTreeMap<u32, PathBuf>, tests: &[u32]) -> Result<(), String> {
+    let missing: Vec<String> = tests
+        .iter()
+        .filter(|test| !outputs.contains_key(test))
+        .map(|test| test.to_string())
+        .collect();
+    let extra: Vec<String> = outputs
+        .keys()
+        .filter(|test| !tests.contains(test))
+        .map(|test| test.to_string())
+        .collect();
+
+    let mut problems = vec![];
+    if !missing.is_empty() {
+        problems.push(format!("missing outputs for tests {}", missing.join(", ")));
+    }
+    if !extra.is_empty() {
+        problems.push(format!("the problem has no tests {}", extra.join(", ")));
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Found {} outputs but the problem has {} tests: {}",
+        outputs.len(),
+        tests.len(),
+        problems.join("; ")
+    ))
+}
+
+/// Packs the outputs into the zip archive the judge expects, keeping their file names
+/// since the problem may expect them zero-padded, e.g. `01.out`.
+pub(crate) fn archive(outputs: &BTreeMap<u32, PathBuf>) -> Result<Vec<u8>, String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for path in outputs.values() {
+        let content = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("{} isn't a file", path.display()))?
+            .to_string_lossy();
+        writer
+            .start_file(name, SimpleFileOptions::default())
+            .map_err(|e| e.to_string())?;
+        writer.write_all(&content).map_err(|e| e.to_string())?;
+    }
+    let archive = writer.finish().map_err(|e| e.to_string())?;
+    Ok(archive.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn outputs(tests: &[u32]) -> BTreeMap<u32, PathBuf> {
+        tests
+            .iter()
+            .map(|&test| (test, PathBuf::from(format!("{}.out", test))))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_matching_outputs() {
+        assert!(validate(&outputs(&[1, 2, 3]), &[1, 2, 3]).is_ok());
+    }
+
+    #[test]
+    fn reports_missing_and_extra_outputs() {
+        let error = validate(&outputs(&[1, 2, 5]), &[1, 2, 3]).unwrap_err();
+        assert!(error.contains("missing outputs for tests 3"));
+        assert!(error.contains("the problem has no tests 5"));
+    }
+
+    #[test]
+    fn keeps_output_file_names() {
+        let dir = TestDir::new("output-only");
+        dir.write("01.out", "1\n");
+        dir.write("2.out", "2\n");
+        dir.write("notes.txt", "");
+
+        let outputs = collect(dir.path()).unwrap();
+        assert_eq!(outputs.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+        let archive = zip::ZipArchive::new(Cursor::new(archive(&outputs).unwrap())).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["01.out", "2.out"]);
+    }
+}
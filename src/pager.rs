@@ -0,0 +1,776 @@
+use super::images::{self, Placement};
+use super::markdown::Document;
+use crossterm::style::Print;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{
+        self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+        enable_raw_mode,
+    },
+};
+use std::collections::HashSet;
+use std::io::{Stdout, Write, stdout};
+use std::panic;
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::UnicodeWidthChar;
+
+/// The narrowest line number column, enough for documents of up to 9999 lines.
+const GUTTER: usize = 5;
+
+const HELP: &str =
+    "/ ? search  n N next  [ ] section  i o e input/output/examples  g G top/bottom  q quit";
+
+/// The text of a line without its escape codes.
+pub(crate) fn strip_ansi(line: &str) -> String {
+    let mut text = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+fn lowercase(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Where `query` appears in a line, as ranges of characters.
+fn find_all(line: &[char], query: &[char]) -> Vec<(usize, usize)> {
+    let mut hits = vec![];
+    let mut start = 0;
+    while !query.is_empty() && start + query.len() <= line.len() {
+        if line[start..start + query.len()] == *query {
+            hits.push((start, start + query.len()));
+            start += query.len();
+        } else {
+            start += 1;
+        }
+    }
+    hits
+}
+
+/// Shows the `hits` of a styled line in reverse video, keeping its other styles.
+fn highlight(line: &str, hits: &[(usize, usize)]) -> String {
+    const ON: &str = "\x1b[7m";
+    const OFF: &str = "\x1b[27m";
+    let inside = |index: usize| {
+        hits.iter()
+            .any(|&(start, end)| start <= index && index < end)
+    };
+
+    let mut output = String::new();
+    let mut index = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            output.push(c);
+            for c in chars.by_ref() {
+                output.push(c);
+                if c == 'm' {
+                    break;
+                }
+            }
+            // A reset in the middle of a hit would end the highlight early.
+            if inside(index) && index > 0 && inside(index - 1) {
+                output.push_str(ON);
+            }
+            continue;
+        }
+        if inside(index) && (index == 0 || !inside(index - 1)) {
+            output.push_str(ON);
+        }
+        output.push(c);
+        index += 1;
+        if inside(index - 1) && !inside(index) {
+            output.push_str(OFF);
+        }
+    }
+    output
+}
+
+/// Common section titles of statements, in Romanian and English.
+fn section_keywords(key: char) -> &'static [&'static str] {
+    match key {
+        'i' => &["intrare", "input"],
+        'o' => &["ieșire", "iesire", "output"],
+        _ => &["exemp", "example"],
+    }
+}
+
+struct Search {
+    query: String,
+    forward: bool,
+    /// Line and character range of every hit, in order.
+    hits: Vec<(usize, (usize, usize))>,
+    current: usize,
+}
+
+/// Lays the content out for a terminal of the given width.
+pub(crate) type Layout<'a> = Box<dyn FnMut(usize) -> Document + 'a>;
+
+/// A document laid out for one width.
+struct View {
+    /// The width of the pane the view was laid out for.
+    pane_width: usize,
+    lines: Vec<String>,
+    /// Lowercase text of the lines, for searching.
+    plain: Vec<Vec<char>>,
+    placements: Vec<Placement>,
+    headings: Vec<(usize, String)>,
+    /// Kitty images already sent to the terminal, by placement.
+    transmitted: HashSet<usize>,
+}
+
+impl View {
+    fn new(document: Document, pane_width: usize) -> Self {
+        Self {
+            pane_width,
+            plain: document
+                .lines
+                .iter()
+                .map(|line| lowercase(&strip_ansi(line)))
+                .collect(),
+            lines: document.lines,
+            placements: document.placements,
+            headings: document.headings,
+            transmitted: HashSet::new(),
+        }
+    }
+
+    fn gutter(&self) -> usize {
+        (self.lines.len().to_string().len() + 1).max(GUTTER)
+    }
+}
+
+/// Where the line `top` of one view is in another: the same offset into the same section when
+/// both have the same sections (as translations do), otherwise the same fraction of the way down.
+fn sync(from: &View, to: &View, top: usize) -> usize {
+    if !from.headings.is_empty() && from.headings.len() == to.headings.len() {
+        return match from.headings.iter().rposition(|(line, _)| *line <= top) {
+            Some(section) => {
+                let start = to.headings[section].0;
+                let end = to
+                    .headings
+                    .get(section + 1)
+                    .map_or(to.lines.len(), |(line, _)| *line);
+                (start + top - from.headings[section].0).min(end.saturating_sub(1).max(start))
+            }
+            None => top.min(to.headings[0].0),
+        };
+    }
+    top * to.lines.len() / from.lines.len().max(1)
+}
+
+struct Pager<'a> {
+    /// Every document with its name, e.g. the language of a statement.
+    documents: Vec<(String, Layout<'a>)>,
+    views: Vec<Option<View>>,
+    active: usize,
+    /// Whether the first two documents are shown side by side.
+    split: bool,
+    top_line: usize,
+    view_height: u16,
+    width: usize,
+    search: Option<Search>,
+    /// The search being typed, with the key that started it.
+    prompt: Option<(char, String)>,
+    message: Option<String>,
+    should_quit: bool,
+}
+
+impl<'a> Pager<'a> {
+    fn new(documents: Vec<(String, Layout<'a>)>, split: bool) -> Self {
+        Self {
+            views: documents.iter().map(|_| None).collect(),
+            split: split && documents.len() > 1,
+            documents,
+            active: 0,
+            top_line: 0,
+            view_height: 0, // Will be set at runtime
+            width: 0,       // Will be set at runtime
+            search: None,
+            prompt: None,
+            message: None,
+            should_quit: false,
+        }
+    }
+
+    fn pane_width(&self) -> usize {
+        if self.split {
+            self.width.saturating_sub(1) / 2
+        } else {
+            self.width
+        }
+    }
+
+    /// Lays a document out for the current pane width, unless it already is.
+    fn lay_out(&mut self, index: usize) {
+        let pane_width = self.pane_width();
+        if self.views[index]
+            .as_ref()
+            .is_some_and(|view| view.pane_width == pane_width)
+        {
+            return;
+        }
+        // The line numbers get wider with the number of lines, which depends on the width
+        // left for the text. Lay it out again until the numbers fit next to it.
+        let mut gutter = GUTTER;
+        loop {
+            let width = pane_width.saturating_sub(gutter + 1).max(10);
+            let view = View::new((self.documents[index].1)(width), pane_width);
+            let needed = view.gutter();
+            self.views[index] = Some(view);
+            if needed <= gutter {
+                break;
+            }
+            gutter = needed;
+        }
+    }
+
+    fn view(&self) -> &View {
+        self.views[self.active]
+            .as_ref()
+            .expect("The active document is laid out")
+    }
+
+    /// Lays the content out again for the current terminal size, staying at the same place in it.
+    fn relayout(&mut self) -> Result<(), std::io::Error> {
+        let (width, height) = terminal::size()?;
+        // The last row is the status line.
+        self.view_height = height.saturating_sub(1).max(1);
+        self.width = width as usize;
+
+        let old_len = self.views[self.active]
+            .as_ref()
+            .map(|view| view.lines.len());
+        let shown = if self.split {
+            0..2
+        } else {
+            self.active..self.active + 1
+        };
+        for index in shown {
+            self.lay_out(index);
+        }
+        if let Some(old_len) = old_len {
+            self.top_line = self.top_line * self.view().lines.len() / old_len.max(1);
+        }
+        self.top_line = self.top_line.min(self.max_top_line());
+
+        if let Some(search) = self.search.take() {
+            self.start_search(search.query, search.forward);
+        }
+        Ok(())
+    }
+
+    /// Switches to the next document, at the same place in it.
+    fn toggle(&mut self) {
+        if self.documents.len() < 2 {
+            self.message = Some("There's nothing to switch to".into());
+            return;
+        }
+        let next = (self.active + 1) % self.documents.len();
+        self.lay_out(next);
+        let top = match &self.views[next] {
+            Some(view) => sync(self.view(), view, self.top_line),
+            None => 0,
+        };
+        self.active = next;
+        self.top_line = top.min(self.max_top_line());
+        if let Some(search) = self.search.take() {
+            self.start_search(search.query, search.forward);
+        }
+    }
+
+    fn run(&mut self) -> Result<(), std::io::Error> {
+        let mut out = stdout();
+        let _terminal = FullScreen::enter(&mut out)?;
+        self.relayout()?;
+
+        while !self.should_quit {
+            self.render(&mut out)?;
+            self.handle_input()?;
+        }
+        Ok(())
+    }
+
+    fn max_top_line(&self) -> usize {
+        self.view()
+            .lines
+            .len()
+            .saturating_sub(self.view_height as usize)
+    }
+
+    fn status(&self) -> String {
+        if let Some((key, query)) = &self.prompt {
+            return format!("{}{}_", key, query);
+        }
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+
+        let total = self.view().lines.len();
+        let bottom = (self.top_line + self.view_height as usize).min(total);
+        let percent = match total {
+            0 => 100,
+            total => bottom * 100 / total,
+        };
+        let mut status = String::new();
+        if self.documents.len() > 1 {
+            status.push_str(&format!("[{}] ", self.documents[self.active].0));
+        }
+        status.push_str(&format!(
+            "lines {}-{} of {} ({}%)",
+            (self.top_line + 1).min(bottom),
+            bottom,
+            total,
+            percent
+        ));
+        if let Some(search) = &self.search {
+            status.push_str(&format!(
+                "  /{}: {} of {}",
+                search.query,
+                search.current + 1,
+                search.hits.len()
+            ));
+        }
+        status
+    }
+
+    /// Draws the lines of a document from `top` down, starting at `column`.
+    fn render_pane(
+        &mut self,
+        out: &mut Stdout,
+        index: usize,
+        top: usize,
+        column: u16,
+    ) -> Result<(), std::io::Error> {
+        let hits: Vec<(usize, (usize, usize))> = match &self.search {
+            Some(search) if index == self.active => search.hits.clone(),
+            _ => vec![],
+        };
+        let height = self.view_height as usize;
+        let Some(view) = self.views[index].as_mut() else {
+            return Ok(());
+        };
+
+        let gutter = view.gutter();
+        let visible = top..(top + height);
+        for (i, line) in view.lines.iter().enumerate().skip(top).take(height) {
+            let line_hits: Vec<(usize, usize)> = hits
+                .iter()
+                .filter(|(hit_line, _)| *hit_line == i)
+                .map(|(_, range)| *range)
+                .collect();
+            let number = format!("{:>width$} ", i + 1, width = gutter - 1);
+            execute!(
+                out,
+                cursor::MoveTo(column, (i - top) as u16),
+                Print(format!("\x1b[90m{}\x1b[0m", number)),
+                Print(highlight(line, &line_hits))
+            )?;
+        }
+
+        for (i, placement) in view.placements.iter().enumerate() {
+            // Only images that fit entirely are drawn, the rest show their placeholder.
+            if placement.line < visible.start
+                || placement.line + placement.graphic.rows as usize > visible.end
+            {
+                continue;
+            }
+            let row = (placement.line - top) as u16;
+            execute!(
+                out,
+                cursor::MoveTo(column + placement.column + gutter as u16, row)
+            )?;
+            placement.graphic.draw(out, view.transmitted.contains(&i))?;
+            view.transmitted.insert(i);
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, out: &mut Stdout) -> Result<(), std::io::Error> {
+        execute!(out, Clear(ClearType::All))?;
+        let kitty = self
+            .views
+            .iter()
+            .flatten()
+            .any(|view| view.placements.iter().any(|p| p.graphic.is_kitty()));
+        if kitty {
+            images::clear_kitty(out)?;
+        }
+
+        if self.split {
+            let pane = self.pane_width() as u16;
+            for index in 0..2 {
+                let top = match (&self.views[self.active], &self.views[index]) {
+                    _ if index == self.active => self.top_line,
+                    (Some(from), Some(to)) => sync(from, to, self.top_line),
+                    _ => 0,
+                };
+                let column = if index == 0 { 0 } else { pane + 1 };
+                self.render_pane(out, index, top, column)?;
+            }
+            for row in 0..self.view_height {
+                execute!(out, cursor::MoveTo(pane, row), Print("\x1b[90m│\x1b[0m"))?;
+            }
+        } else {
+            self.render_pane(out, self.active, self.top_line, 0)?;
+        }
+
+        let width = self.width;
+        let status = self.status();
+        let used = status.chars().count();
+        let help = if self.documents.len() > 1 {
+            format!("{}  t language", HELP)
+        } else {
+            HELP.to_string()
+        };
+        let help = if used + 2 + help.len() <= width {
+            format!("{:>pad$}", help, pad = width - used)
+        } else {
+            String::new()
+        };
+        let line: String = format!("{}{}", status, help).chars().take(width).collect();
+        execute!(
+            out,
+            cursor::MoveTo(0, self.view_height),
+            Print(format!("\x1b[7m{:<width$}\x1b[0m", line, width = width))
+        )?;
+
+        out.flush()
+    }
+
+    /// Scrolls just enough for `line` to be visible, with a little context above it.
+    fn reveal(&mut self, line: usize) {
+        let height = self.view_height as usize;
+        if line < self.top_line || line >= self.top_line + height {
+            self.top_line = line.saturating_sub(2).min(self.max_top_line());
+        }
+    }
+
+    fn start_search(&mut self, query: String, forward: bool) {
+        let needle = lowercase(&query);
+        let hits: Vec<(usize, (usize, usize))> = self
+            .view()
+            .plain
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| find_all(line, &needle).into_iter().map(move |hit| (i, hit)))
+            .collect();
+        if hits.is_empty() {
+            self.message = Some(format!("Pattern not found: {}", query));
+            self.search = None;
+            return;
+        }
+
+        let bottom = self.top_line + self.view_height as usize;
+        let current = if forward {
+            hits.iter()
+                .position(|(line, _)| *line >= self.top_line)
+                .unwrap_or(0)
+        } else {
+            hits.iter()
+                .rposition(|(line, _)| *line < bottom)
+                .unwrap_or(hits.len() - 1)
+        };
+        self.reveal(hits[current].0);
+        self.search = Some(Search {
+            query,
+            forward,
+            hits,
+            current,
+        });
+    }
+
+    /// `n` goes on in the direction of the search, `N` goes back.
+    fn next_hit(&mut self, same_direction: bool) {
+        let Some(search) = &mut self.search else {
+            self.message = Some("No previous search".into());
+            return;
+        };
+        let count = search.hits.len();
+        search.current = if search.forward == same_direction {
+            (search.current + 1) % count
+        } else {
+            (search.current + count - 1) % count
+        };
+        let line = search.hits[search.current].0;
+        self.reveal(line);
+    }
+
+    fn jump_to_heading(&mut self, next: bool) {
+        let heading = if next {
+            self.view()
+                .headings
+                .iter()
+                .find(|(line, _)| *line > self.top_line)
+        } else {
+            self.view()
+                .headings
+                .iter()
+                .rev()
+                .find(|(line, _)| *line < self.top_line)
+        };
+        match heading {
+            Some(&(line, _)) => self.top_line = line.min(self.max_top_line()),
+            None => self.message = Some("No more sections".into()),
+        }
+    }
+
+    fn jump_to_section(&mut self, key: char) {
+        let keywords = section_keywords(key);
+        let heading = self.view().headings.iter().find(|(_, title)| {
+            let title = title.to_lowercase();
+            keywords.iter().any(|keyword| title.contains(keyword))
+        });
+        match heading {
+            Some(&(line, _)) => self.top_line = line.min(self.max_top_line()),
+            None => self.message = Some("This statement has no such section".into()),
+        }
+    }
+
+    fn handle_prompt(&mut self, code: KeyCode) {
+        let Some((key, query)) = &mut self.prompt else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) => query.push(c),
+            KeyCode::Backspace if query.is_empty() => self.prompt = None,
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Enter => {
+                let forward = *key == '/';
+                let query = std::mem::take(query);
+                self.prompt = None;
+                if !query.is_empty() {
+                    self.start_search(query, forward);
+                }
+            }
+            KeyCode::Esc => self.prompt = None,
+            _ => {}
+        }
+    }
+
+    fn handle_input(&mut self) -> Result<(), std::io::Error> {
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Resize(..) => return self.relayout(),
+            _ => return Ok(()),
+        };
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return Ok(());
+        }
+        if self.prompt.is_some() {
+            self.handle_prompt(key.code);
+            return Ok(());
+        }
+
+        self.message = None;
+        let page = self.view_height as usize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.top_line = self.top_line.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.top_line = (self.top_line + 1).min(self.max_top_line());
+            }
+            KeyCode::PageUp => {
+                self.top_line = self.top_line.saturating_sub(page);
+            }
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.top_line = (self.top_line + page).min(self.max_top_line());
+            }
+            KeyCode::Home | KeyCode::Char('g') => self.top_line = 0,
+            KeyCode::End | KeyCode::Char('G') => self.top_line = self.max_top_line(),
+            KeyCode::Char(c @ ('/' | '?')) => self.prompt = Some((c, String::new())),
+            KeyCode::Char('n') => self.next_hit(true),
+            KeyCode::Char('N') => self.next_hit(false),
+            KeyCode::Char(']') => self.jump_to_heading(true),
+            KeyCode::Char('[') => self.jump_to_heading(false),
+            KeyCode::Char(c @ ('i' | 'o' | 'e')) => self.jump_to_section(c),
+            KeyCode::Char('t') => self.toggle(),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Hard-wraps a line with escape codes to `width` columns, carrying its style over to the next line.
+fn wrap_ansi(line: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+    let mut current_width = 0;
+    // The escape codes in effect, repeated at the start of every continuation line.
+    let mut active = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut sequence = c.to_string();
+            for c in chars.by_ref() {
+                sequence.push(c);
+                if c == 'm' {
+                    break;
+                }
+            }
+            if sequence == "\x1b[0m" {
+                active.clear();
+            } else {
+                active.push_str(&sequence);
+            }
+            current.push_str(&sequence);
+            continue;
+        }
+        let char_width = c.width().unwrap_or(0);
+        if current_width > 0 && current_width + char_width > width {
+            if !active.is_empty() {
+                current.push_str("\x1b[0m");
+            }
+            lines.push(std::mem::replace(&mut current, active.clone()));
+            current_width = 0;
+        }
+        current.push(c);
+        current_width += char_width;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Raw mode on the alternate screen, left when dropped or when the program panics.
+struct FullScreen;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn restore_terminal() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout(), LeaveAlternateScreen, cursor::Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+impl FullScreen {
+    fn enter(out: &mut Stdout) -> Result<Self, std::io::Error> {
+        static PANIC_HOOK: Once = Once::new();
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                // Otherwise the message would be printed on the alternate screen and lost.
+                restore_terminal();
+                previous(info);
+            }));
+        });
+
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        execute!(out, EnterAlternateScreen, cursor::Hide)?;
+        Ok(FullScreen)
+    }
+}
+
+impl Drop for FullScreen {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Shows text (escape codes included) in the pager, wrapping long lines.
+pub(crate) fn page(content: &str) {
+    show(|width| Document {
+        lines: content
+            .lines()
+            .flat_map(|line| wrap_ansi(line, width))
+            .collect(),
+        placements: vec![],
+        headings: vec![],
+    });
+}
+
+/// Shows a document in the full-screen pager, laid out again every time the terminal is resized.
+pub(crate) fn show<'a>(layout: impl FnMut(usize) -> Document + 'a) {
+    show_all(vec![(String::new(), Box::new(layout))], false);
+}
+
+/// Shows several versions of a document, e.g. the translations of a statement, toggled with `t`.
+/// With `split`, the first two are shown side by side and scroll together.
+pub(crate) fn show_all(documents: Vec<(String, Layout)>, split: bool) {
+    let mut pager = Pager::new(documents, split);
+    if let Err(e) = pager.run() {
+        eprintln!("Pager Error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_hits_across_styles() {
+        let line = "a \x1b[1mbold\x1b[0m word";
+        let hits = find_all(&lowercase(&strip_ansi(line)), &lowercase("LD W"));
+        assert_eq!(hits, vec![(4, 8)]);
+        assert_eq!(
+            highlight(line, &hits),
+            "a \x1b[1mbo\x1b[7mld\x1b[0m\x1b[7m w\x1b[27mord"
+        );
+    }
+
+    #[test]
+    fn wraps_styled_lines() {
+        assert_eq!(
+            wrap_ansi("ab\x1b[31mcdef\x1b[0mg", 3),
+            vec!["ab\x1b[31mc\x1b[0m", "\x1b[31mdef\x1b[0m", "g"]
+        );
+    }
+
+    #[test]
+    fn syncs_translations_by_section() {
+        let view = |lines: usize, headings: &[usize]| {
+            View::new(
+                Document {
+                    lines: vec![String::new(); lines],
+                    placements: vec![],
+                    headings: headings.iter().map(|&line| (line, String::new())).collect(),
+                },
+                80,
+            )
+        };
+        let ro = view(30, &[0, 10, 20]);
+        let en = view(24, &[0, 6, 16]);
+        assert_eq!(sync(&ro, &en, 12), 8);
+        // Past the end of a shorter section, the other one stays at its last line.
+        assert_eq!(sync(&en, &ro, 5), 5);
+        assert_eq!(sync(&ro, &en, 9), 5);
+        assert_eq!(sync(&ro, &view(15, &[]), 20), 10);
+    }
+
+    #[test]
+    fn leaves_room_for_wide_line_numbers() {
+        // Ten thousand lines need six columns of line numbers, one more than usual.
+        let layout: Layout = Box::new(|width| Document {
+            lines: vec!["x".repeat(width); 12_000],
+            placements: vec![],
+            headings: vec![],
+        });
+        let mut pager = Pager::new(vec![("ro".into(), layout)], false);
+        pager.width = 80;
+        pager.lay_out(0);
+        let view = pager.view();
+        assert_eq!(view.gutter(), 6);
+        assert!(
+            view.lines
+                .iter()
+                .all(|line| view.gutter() + 1 + line.len() <= 80)
+        );
+    }
+}
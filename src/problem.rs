@@ -0,0 +1,375 @@
+use super::{archive, credential_manager, waiter};
+use base64::Engine;
+use base64::engine::general_purpose;
+use colored::Colorize;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+struct AttachmentData {
+    data: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct AttachmentResponse {
+    data: AttachmentData,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Attachment {
+    pub name: String,
+    #[serde(default)]
+    pub private: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct AttachmentsResponse {
+    data: Vec<Attachment>,
+}
+
+/// Sends the session token along when logged in, so problems in running contests are visible.
+pub(crate) fn authorized(request: RequestBuilder) -> RequestBuilder {
+    match credential_manager::CredentialManager::global().get::<credential_manager::Token>() {
+        Some(token) => request.header("Authorization", token),
+        None => request,
+    }
+}
+
+pub(crate) fn attachment(
+    client: &Client,
+    id: &str,
+    name: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let url = format!(
+        "https://kilonova.ro/api/problem/{}/get/attachmentByName/{}",
+        id, name
+    );
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err("Failed to fetch from API".into());
+    }
+    let text = response.text()?;
+    if text.trim().is_empty() {
+        return Err("Empty response".into());
+    }
+    let api_response: AttachmentResponse = serde_json::from_str(&text).map_err(|e| {
+        format!(
+            "JSON parsing error: '{}' on text starting with: '{}'",
+            e,
+            text.chars().take(100).collect::<String>()
+        )
+    })?;
+    Ok(general_purpose::STANDARD.decode(api_response.data.data)?)
+}
+
+pub(crate) fn attachments(
+    client: &Client,
+    id: &str,
+) -> Result<Vec<Attachment>, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/get/attachments", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't list the attachments: {}", response.text()?).into());
+    }
+    let api_response: AttachmentsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response.data)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct ProblemDetails {
+    #[serde(default)]
+    pub id: u64,
+    #[serde(default)]
+    pub name: String,
+    /// In seconds.
+    #[serde(default)]
+    pub time_limit: f64,
+    /// In kilobytes.
+    #[serde(default)]
+    pub memory_limit: u64,
+    #[serde(default)]
+    pub source_credits: String,
+    #[serde(default = "default_score_scale")]
+    pub score_scale: f64,
+    #[serde(default)]
+    pub source_size: u64,
+    #[serde(default)]
+    pub console_input: bool,
+    #[serde(default)]
+    pub test_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DetailsResponse {
+    data: ProblemDetails,
+}
+
+pub(crate) fn details(
+    client: &Client,
+    id: &str,
+) -> Result<ProblemDetails, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't get the problem: {}", response.text()?).into());
+    }
+    let api_response: DetailsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response.data)
+}
+
+fn default_score_scale() -> f64 {
+    100.0
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Tag {
+    #[serde(default)]
+    pub id: u64,
+    pub name: String,
+    /// `author`, `contest`, `method` or `other`.
+    #[serde(rename = "type", default)]
+    pub kind: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagsResponse {
+    data: Vec<Tag>,
+}
+
+pub(crate) fn tags(client: &Client, id: &str) -> Result<Vec<Tag>, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/tags", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't get the tags: {}", response.text()?).into());
+    }
+    let api_response: TagsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response.data)
+}
+
+/// Every tag on the site.
+pub(crate) fn all_tags(client: &Client) -> Result<Vec<Tag>, Box<dyn std::error::Error>> {
+    let response = client.get("https://kilonova.ro/api/tags/").send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't get the tags: {}", response.text()?).into());
+    }
+    let api_response: TagsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response.data)
+}
+
+#[derive(Deserialize, Debug)]
+struct ScoreResponse {
+    data: f64,
+}
+
+/// The logged in user's best score on the problem, `None` if they never submitted.
+pub(crate) fn best_score(
+    client: &Client,
+    id: &str,
+) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/maxScore", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't get the score: {}", response.text()?).into());
+    }
+    let api_response: ScoreResponse = serde_json::from_str(&response.text()?)?;
+    Ok(Some(api_response.data).filter(|score| *score >= 0.0))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Contest {
+    pub name: String,
+    pub start_time: String,
+    pub end_time: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContestsResponse {
+    data: Vec<Contest>,
+}
+
+/// The contests the problem is part of that are visible to the user.
+pub(crate) fn contests(
+    client: &Client,
+    id: &str,
+) -> Result<Vec<Contest>, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/contests", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't list the contests: {}", response.text()?).into());
+    }
+    let api_response: ContestsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response.data)
+}
+
+#[derive(Deserialize, Debug)]
+struct TestData {
+    visible_id: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestsResponse {
+    data: Vec<TestData>,
+}
+
+/// The numbers of the problem's tests, as shown in verdicts.
+pub(crate) fn test_ids(client: &Client, id: &str) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let url = format!("https://kilonova.ro/api/problem/{}/get/tests", id);
+    let response = authorized(client.get(url)).send()?;
+    if !response.status().is_success() {
+        return Err(format!("Couldn't list the tests: {}", response.text()?).into());
+    }
+    let api_response: TestsResponse = serde_json::from_str(&response.text()?)?;
+    Ok(api_response
+        .data
+        .into_iter()
+        .map(|t| t.visible_id)
+        .collect())
+}
+
+/// The grader sources, compiled together with the contestant's file.
+const GRADER_SOURCES: [&str; 3] = ["grader.cpp", "grader.cc", "grader.cxx"];
+
+/// Grader problems ship the `main` and the function declarations, the contestant only
+/// implements the functions. The declarations are in `grader.h` or, often, in a header named
+/// after the problem such as `cities.h`, so every header among the `attachments` counts too.
+pub(crate) fn is_grader_file(name: &str, attachments: &[Attachment]) -> bool {
+    let lowercase = name.to_lowercase();
+    GRADER_SOURCES.contains(&lowercase.as_str())
+        || lowercase == "grader.h"
+        || lowercase.ends_with(".h") && attachments.iter().any(|a| a.name == name)
+}
+
+pub(crate) fn is_sample_test(name: &str) -> bool {
+    [".in", ".out", ".ok"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Grader sources next to the solution, compiled together with it for local runs.
+pub(crate) fn grader_sources(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut sources: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| GRADER_SOURCES.contains(&name.to_lowercase().as_str()))
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+pub fn download(dir: PathBuf) {
+    let problem =
+        credential_manager::CredentialManager::global().get::<credential_manager::Cache>();
+    let problem = match problem {
+        Some(p) => p,
+        None => {
+            println!(
+                "{}",
+                "Downloads are made from the last seen problem. You need to look at a problem first."
+                    .bright_yellow()
+            );
+            return;
+        }
+    };
+
+    let spinner = waiter::Waiter::start();
+    let client = Client::new();
+    let list = attachments(&client, &problem);
+    let list = match list {
+        Ok(list) => list,
+        Err(e) => {
+            spinner.stop();
+            println!("{} {}", "Couldn't download the attachments : ".red(), e);
+            return;
+        }
+    };
+
+    let mut saved = vec![];
+    let mut graders = vec![];
+    let mut failed = vec![];
+    for file in list
+        .iter()
+        .filter(|a| !a.private && !a.name.starts_with("statement-"))
+    {
+        if !archive::is_plain_name(&file.name) {
+            failed.push((file.name.clone(), "not a plain file name".to_string()));
+            continue;
+        }
+        let target = if is_sample_test(&file.name) {
+            dir.join("tests").join(&file.name)
+        } else {
+            dir.join(&file.name)
+        };
+
+        let written = attachment(&client, &problem, &file.name)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                fs::create_dir_all(target.parent().unwrap()).map_err(|e| e.to_string())?;
+                fs::write(&target, data).map_err(|e| e.to_string())
+            });
+        match written {
+            Ok(()) => {
+                if is_grader_file(&file.name, &list) {
+                    graders.push(file.name.clone());
+                }
+                saved.push(target);
+            }
+            Err(e) => failed.push((file.name.clone(), e)),
+        }
+    }
+    spinner.stop();
+
+    for (name, e) in &failed {
+        println!("{} {}: {}", "Couldn't save".red(), name, e);
+    }
+
+    if saved.is_empty() && failed.is_empty() {
+        println!(
+            "{}",
+            "This problem has no downloadable attachments".yellow()
+        );
+        return;
+    }
+    for path in &saved {
+        println!("Saved {}", path.display());
+    }
+    if !graders.is_empty() {
+        println!(
+            "{} {}",
+            "This problem uses a grader:".bright_yellow(),
+            graders.join(", ")
+        );
+        println!(
+            "{}",
+            "Keep your solution next to the grader: `test` compiles them together, `submit` only sends your file."
+                .bright_yellow()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_grader_attachments() {
+        let attachments = [Attachment {
+            name: "cities.h".into(),
+            private: false,
+        }];
+        assert!(is_grader_file("grader.cpp", &[]));
+        assert!(is_grader_file("Grader.cc", &[]));
+        assert!(is_grader_file("grader.h", &[]));
+        assert!(is_grader_file("cities.h", &attachments));
+        assert!(!is_grader_file("cities.h", &[]));
+        assert!(!is_grader_file("grader.py", &attachments));
+        assert!(!is_grader_file("statement-ro.md", &attachments));
+        assert!(!is_grader_file("1.in", &attachments));
+    }
+}
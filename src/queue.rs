@@ -0,0 +1,288 @@
+use super::config::{self, Config};
+use super::hooks::HookContext;
+use super::submission_index::SubmissionIndex;
+use super::submitter::{self, Payload, UploadError};
+use super::{credential_manager, storage};
+use base64::Engine;
+use base64::engine::general_purpose;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILE: &str = "queue.json";
+
+/// A submission waiting for the network, with a snapshot of what would have been uploaded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct QueuedSubmission {
+    pub problem: String,
+    pub language: String,
+    pub source: PathBuf,
+    pub file_name: String,
+    pub mime: String,
+    /// Base64 of the uploaded bytes.
+    pub content: String,
+    pub hash: String,
+    /// Seconds since the Unix epoch.
+    pub queued_at: u64,
+}
+
+impl QueuedSubmission {
+    fn payload(&self) -> Result<Payload, String> {
+        let content = general_purpose::STANDARD
+            .decode(&self.content)
+            .map_err(|e| e.to_string())?;
+        Ok(Payload {
+            content,
+            file_name: self.file_name.clone(),
+            mime: self.mime.clone(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Queue {
+    pub entries: Vec<QueuedSubmission>,
+}
+
+impl Queue {
+    pub(crate) fn load() -> Result<Self, String> {
+        storage::load(FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), String> {
+        storage::save(FILE, self)
+    }
+}
+
+/// Appends a submission to the queue, returning its position.
+pub(crate) fn push(
+    problem: &str,
+    language: &str,
+    source: &Path,
+    payload: Payload,
+    hash: String,
+) -> Result<usize, String> {
+    let mut queue = Queue::load()?;
+    queue.entries.push(QueuedSubmission {
+        problem: problem.to_string(),
+        language: language.to_string(),
+        source: source.to_path_buf(),
+        file_name: payload.file_name,
+        mime: payload.mime,
+        content: general_purpose::STANDARD.encode(&payload.content),
+        hash,
+        queued_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    });
+    queue.save()?;
+    Ok(queue.entries.len())
+}
+
+pub(crate) fn is_empty() -> bool {
+    Queue::load().map_or(true, |queue| queue.entries.is_empty())
+}
+
+pub(crate) fn age(queued_at: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let seconds = now.saturating_sub(queued_at);
+    match seconds {
+        0..60 => format!("{}s ago", seconds),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+pub fn list() {
+    let queue = match Queue::load() {
+        Ok(queue) => queue,
+        Err(e) => {
+            println!("{} {}", "Couldn't read the queue :".red(), e);
+            return;
+        }
+    };
+    if queue.entries.is_empty() {
+        println!("{}", "The submission queue is empty".green());
+        return;
+    }
+    for (position, entry) in queue.entries.iter().enumerate() {
+        println!(
+            "{:>3}. problem #{} {} {} ({})",
+            position + 1,
+            entry.problem,
+            entry.language.bright_blue(),
+            entry.source.display(),
+            age(entry.queued_at)
+        );
+    }
+}
+
+/// Uploads the queued submissions in order and reports their verdicts.
+/// Stops at the first network error, keeping that entry and the ones after it.
+pub fn flush() {
+    let mut queue = match Queue::load() {
+        Ok(queue) => queue,
+        Err(e) => {
+            println!("{} {}", "Couldn't read the queue :".red(), e);
+            return;
+        }
+    };
+    if queue.entries.is_empty() {
+        println!("{}", "The submission queue is empty".green());
+        return;
+    }
+
+    let token = credential_manager::CredentialManager::global().get::<credential_manager::Token>();
+    let token = match token {
+        Some(t) => t,
+        None => {
+            println!(
+                "{}",
+                "You need to be logged in before you can submit".bright_yellow()
+            );
+            return;
+        }
+    };
+
+    let config = config::load().unwrap_or_else(|e| {
+        println!("{} {}", "Couldn't read the configuration :".yellow(), e);
+        Config::default()
+    });
+    let mut index = SubmissionIndex::load().unwrap_or_else(|e| {
+        println!("{} {}", "Couldn't read the submission index :".yellow(), e);
+        SubmissionIndex::default()
+    });
+
+    println!(
+        "{}",
+        format!("Uploading {} queued submissions...", queue.entries.len()).green()
+    );
+    while let Some(entry) = queue.entries.first().cloned() {
+        println!(
+            "{} problem #{} ({})",
+            "Submitting".bold(),
+            entry.problem,
+            entry.source.display()
+        );
+        let uploaded = entry
+            .payload()
+            .map_err(UploadError::Rejected)
+            .and_then(|payload| {
+                submitter::upload(&token, &entry.problem, &entry.language, &payload)
+            });
+
+        let submission_id = match uploaded {
+            Ok(id) => Some(id),
+            Err(UploadError::Unknown(e)) => {
+                // Sending it again could submit the same code twice.
+                queue.entries.remove(0);
+                if let Err(e) = queue.save() {
+                    println!("{} {}", "Couldn't update the queue :".red(), e);
+                }
+                println!("{} {}", "Couldn't confirm the submission :".red(), e);
+                println!(
+                    "{}",
+                    format!(
+                        "It may have gone through, so it left the queue. Check your submissions on Kilonova and submit {} again if it's missing.",
+                        entry.source.display()
+                    )
+                    .bright_yellow()
+                );
+                return;
+            }
+            Err(UploadError::Network(e)) => {
+                println!("{} {}", "Still offline :".red(), e);
+                println!(
+                    "{}",
+                    format!("{} submissions left in the queue", queue.entries.len())
+                        .bright_yellow()
+                );
+                return;
+            }
+            Err(UploadError::Unavailable(e)) => {
+                println!("{} {}", "Kilonova can't take it right now :".red(), e);
+                println!(
+                    "{}",
+                    format!(
+                        "Kept {} submissions in the queue, log in again if your session expired and flush later",
+                        queue.entries.len()
+                    )
+                    .bright_yellow()
+                );
+                return;
+            }
+            Err(UploadError::Rejected(e)) => {
+                println!(
+                    "{} problem #{} ({}) : {}",
+                    "Kilonova rejected it, dropped it from the queue :".red(),
+                    entry.problem,
+                    entry.source.display(),
+                    e
+                );
+                None
+            }
+        };
+
+        queue.entries.remove(0);
+        if let Err(e) = queue.save() {
+            println!("{} {}", "Couldn't update the queue :".red(), e);
+            return;
+        }
+
+        if let Some(submission_id) = submission_id {
+            index.record(&entry.problem, entry.hash.clone(), submission_id);
+            submitter::save_index(&index);
+            let hook_context = HookContext {
+                problem: &entry.problem,
+                source: &entry.source,
+                language: &entry.language,
+            };
+            submitter::judge(submission_id, &mut index, &config, &hook_context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_payload() {
+        let entry = QueuedSubmission {
+            problem: "42".to_string(),
+            language: "cpp17".to_string(),
+            source: PathBuf::from("main.cpp"),
+            file_name: "main.cpp".to_string(),
+            mime: "text/plain".to_string(),
+            content: general_purpose::STANDARD.encode("int main() {}"),
+            hash: "0".to_string(),
+            queued_at: 0,
+        };
+        let json = serde_json::to_string(&Queue {
+            entries: vec![entry.clone()],
+        })
+        .unwrap();
+        let queue: Queue = serde_json::from_str(&json).unwrap();
+        assert_eq!(queue.entries, vec![entry]);
+        assert_eq!(
+            queue.entries[0].payload().unwrap().content,
+            b"int main() {}"
+        );
+    }
+
+    #[test]
+    fn formats_ages() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert_eq!(age(now - 5), "5s ago");
+        assert_eq!(age(now - 7200), "2h ago");
+    }
+}
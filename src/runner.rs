@@ -0,0 +1,738 @@
+use super::{credential_manager, diff, pager, problem, submission_index, watch};
+use colored::Colorize;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub(crate) struct Executable {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl Executable {
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+}
+
+pub(crate) struct TestCase {
+    pub name: String,
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+}
+
+pub(crate) enum Verdict {
+    Accepted,
+    WrongAnswer { expected: String, actual: String },
+    TimeLimitExceeded,
+    RuntimeError(String),
+    Rejected { reason: String, transcript: String },
+}
+
+pub(crate) struct TestResult {
+    pub name: String,
+    pub verdict: Verdict,
+    pub time: Duration,
+}
+
+fn build_root() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join("kilo-cli-build");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Where the binaries and transcripts of `source` go. Every source file gets its own
+/// directory, so two solutions called `main.cpp` don't overwrite each other's files.
+fn build_dir(source: &Path) -> Result<PathBuf, String> {
+    let source = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+    let hash = submission_index::content_hash(source.as_os_str().as_encoded_bytes());
+    let dir = build_root()?.join(hash);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn run_compiler(command: &mut Command) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("Couldn't start the compiler: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+/// Compiles `source` for the given submission language into `dir`, returning how to run it.
+/// Interpreted languages are run straight from the source file. C++ solutions are
+/// linked with any `grader.*` source found next to them.
+pub(crate) fn compile(source: &Path, language: &str, dir: &Path) -> Result<Executable, String> {
+    let source = source.to_path_buf();
+    let stem = source
+        .file_stem()
+        .ok_or("Invalid source file name")?
+        .to_os_string();
+    let binary = dir.join(&stem);
+
+    let standard = match language {
+        "cpp11" => Some("-std=c++11"),
+        "cpp13" => Some("-std=c++14"),
+        "cpp17" => Some("-std=c++17"),
+        "cpp20" => Some("-std=c++20"),
+        _ => None,
+    };
+
+    let compiled = |binary: PathBuf| Executable {
+        program: binary.into_os_string(),
+        args: vec![],
+    };
+
+    if let Some(standard) = standard {
+        let dir = source.parent().unwrap_or(Path::new("."));
+        let graders: Vec<PathBuf> = problem::grader_sources(dir)
+            .into_iter()
+            .filter(|grader| *grader != source)
+            .collect();
+        run_compiler(
+            Command::new("g++")
+                .arg(standard)
+                .args(["-O2", "-o"])
+                .arg(&binary)
+                .arg("-I")
+                .arg(dir)
+                .arg(&source)
+                .args(&graders),
+        )?;
+        return Ok(compiled(binary));
+    }
+
+    match language {
+        "rust" => {
+            run_compiler(
+                Command::new("rustc")
+                    .args(["--edition", "2021", "-O", "-o"])
+                    .arg(&binary)
+                    .arg(&source),
+            )?;
+            Ok(compiled(binary))
+        }
+        "go" => {
+            run_compiler(
+                Command::new("go")
+                    .args(["build", "-o"])
+                    .arg(&binary)
+                    .arg(&source),
+            )?;
+            Ok(compiled(binary))
+        }
+        "pascal" => {
+            let mut output = OsString::from("-o");
+            output.push(&binary);
+            run_compiler(Command::new("fpc").arg("-O2").arg(output).arg(&source))?;
+            Ok(compiled(binary))
+        }
+        "kotlin" => {
+            let jar = binary.with_extension("jar");
+            run_compiler(
+                Command::new("kotlinc")
+                    .arg(&source)
+                    .args(["-include-runtime", "-d"])
+                    .arg(&jar),
+            )?;
+            Ok(Executable {
+                program: "java".into(),
+                args: vec!["-jar".into(), jar.into_os_string()],
+            })
+        }
+        "python3" | "node.js" | "php" => {
+            let interpreter = match language {
+                "python3" => "python3",
+                "node.js" => "node",
+                _ => "php",
+            };
+            Ok(Executable {
+                program: interpreter.into(),
+                args: vec![source.into_os_string()],
+            })
+        }
+        _ => Err(format!("Can't run `{}` solutions locally", language)),
+    }
+}
+
+/// Compiles `source` without producing a binary, for languages that have such a mode.
+pub(crate) fn syntax_check(source: &str, language: &str) -> Result<(), String> {
+    let (extension, program, args): (&str, &str, &[&str]) = match language {
+        "cpp11" => ("cpp", "g++", &["-std=c++11", "-fsyntax-only"]),
+        "cpp13" => ("cpp", "g++", &["-std=c++14", "-fsyntax-only"]),
+        "cpp17" => ("cpp", "g++", &["-std=c++17", "-fsyntax-only"]),
+        "cpp20" => ("cpp", "g++", &["-std=c++20", "-fsyntax-only"]),
+        "rust" => (
+            "rs",
+            "rustc",
+            &["--edition", "2021", "--emit=metadata", "--out-dir"],
+        ),
+        "python3" => ("py", "python3", &["-m", "py_compile"]),
+        "go" => ("go", "gofmt", &["-e", "-l"]),
+        "php" => ("php", "php", &["-l"]),
+        "node.js" => ("js", "node", &["--check"]),
+        _ => return Ok(()),
+    };
+
+    let dir = build_root()?;
+    let file = dir.join(format!("syntax-check.{}", extension));
+    fs::write(&file, source).map_err(|e| e.to_string())?;
+    let mut command = Command::new(program);
+    command.args(args);
+    if language == "rust" {
+        command.arg(&dir);
+    }
+    run_compiler(command.arg(&file))
+}
+
+fn natural_key(name: &str) -> (u64, String) {
+    (name.parse().unwrap_or(u64::MAX), name.to_string())
+}
+
+/// Collects the `<name>.in` files in `dir` that have a matching `<name>.out` or `<name>.ok`.
+/// Interactive tests don't need an expected output, the interactor decides the verdict.
+pub(crate) fn discover_tests(dir: &Path, interactive: bool) -> Result<Vec<TestCase>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Couldn't read the tests directory {}: {}", dir.display(), e))?;
+
+    let mut tests: Vec<TestCase> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+        .filter_map(|input| {
+            let output = ["out", "ok"]
+                .iter()
+                .map(|ext| input.with_extension(ext))
+                .find(|path| path.is_file());
+            if output.is_none() && !interactive {
+                return None;
+            }
+            let name = input.file_stem()?.to_string_lossy().into_owned();
+            Some(TestCase {
+                name,
+                input,
+                output,
+            })
+        })
+        .collect();
+
+    tests.sort_by_key(|test| natural_key(&test.name));
+    Ok(tests)
+}
+
+fn run_one(executable: &Executable, test: &TestCase, time_limit: Duration) -> TestResult {
+    let result = |verdict, time| TestResult {
+        name: test.name.clone(),
+        verdict,
+        time,
+    };
+
+    let input = match fs::read(&test.input) {
+        Ok(input) => input,
+        Err(e) => return result(Verdict::RuntimeError(e.to_string()), Duration::ZERO),
+    };
+
+    let start = Instant::now();
+    let child = executable
+        .command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => return result(Verdict::RuntimeError(e.to_string()), Duration::ZERO),
+    };
+
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        output
+    });
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if start.elapsed() > time_limit => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(5)),
+            Err(e) => return result(Verdict::RuntimeError(e.to_string()), start.elapsed()),
+        }
+    };
+    let elapsed = start.elapsed();
+    let _ = writer.join();
+    let output = reader.join().unwrap_or_default();
+
+    let status = match status {
+        Some(status) => status,
+        None => return result(Verdict::TimeLimitExceeded, elapsed),
+    };
+    if !status.success() {
+        return result(Verdict::RuntimeError(status.to_string()), elapsed);
+    }
+
+    let expected = test
+        .output
+        .as_ref()
+        .and_then(|output| fs::read_to_string(output).ok())
+        .unwrap_or_default();
+    let actual = String::from_utf8_lossy(&output).into_owned();
+    match diff::first_mismatch(&expected, &actual) {
+        None => result(Verdict::Accepted, elapsed),
+        Some(_) => result(Verdict::WrongAnswer { expected, actual }, elapsed),
+    }
+}
+
+/// Interactors are usually testlib C++ sources, anything else is run as it is.
+/// They are compiled on their own, without looking for a grader.
+fn interactor_executable(path: &Path) -> Result<Executable, String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("cpp" | "cc" | "cxx") => {
+            let stem = path.file_stem().ok_or("Invalid interactor file name")?;
+            let binary = build_dir(path)?.join(stem);
+            run_compiler(
+                Command::new("g++")
+                    .args(["-std=c++17", "-O2", "-o"])
+                    .arg(&binary)
+                    .arg("-I")
+                    .arg(path.parent().unwrap_or(Path::new(".")))
+                    .arg(path),
+            )?;
+            Ok(Executable {
+                program: binary.into_os_string(),
+                args: vec![],
+            })
+        }
+        _ => Ok(Executable {
+            program: path.as_os_str().to_os_string(),
+            args: vec![],
+        }),
+    }
+}
+
+/// Forwards `from` into `to` line by line, recording every line in the shared transcript.
+fn relay(
+    from: impl Read + Send + 'static,
+    mut to: impl Write + Send + 'static,
+    marker: &'static str,
+    transcript: Arc<Mutex<Vec<String>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut from = BufReader::new(from);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match from.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            transcript.lock().unwrap().push(format!(
+                "{} {}",
+                marker,
+                String::from_utf8_lossy(&line).trim_end()
+            ));
+            if to.write_all(&line).and_then(|_| to.flush()).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Runs the solution with its stdin and stdout wired to the interactor's stdout and stdin.
+/// The interactor gets the test input (and the expected output, if any) as arguments and
+/// its exit code is the verdict. The transcript is saved in `dir` as `<name>.log`.
+fn run_interactive(
+    executable: &Executable,
+    interactor: &Executable,
+    test: &TestCase,
+    time_limit: Duration,
+    dir: &Path,
+) -> TestResult {
+    let result = |verdict, time| TestResult {
+        name: test.name.clone(),
+        verdict,
+        time,
+    };
+
+    let mut interactor_command = interactor.command();
+    interactor_command.arg(&test.input);
+    if let Some(output) = &test.output {
+        interactor_command.arg(output);
+    }
+    let interactor = interactor_command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut interactor = match interactor {
+        Ok(child) => child,
+        Err(e) => return result(Verdict::RuntimeError(e.to_string()), Duration::ZERO),
+    };
+
+    let start = Instant::now();
+    let solution = executable
+        .command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut solution = match solution {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = interactor.kill();
+            let _ = interactor.wait();
+            return result(Verdict::RuntimeError(e.to_string()), Duration::ZERO);
+        }
+    };
+
+    let transcript = Arc::new(Mutex::new(Vec::new()));
+    let relays = [
+        relay(
+            interactor.stdout.take().unwrap(),
+            solution.stdin.take().unwrap(),
+            ">",
+            transcript.clone(),
+        ),
+        relay(
+            solution.stdout.take().unwrap(),
+            interactor.stdin.take().unwrap(),
+            "<",
+            transcript.clone(),
+        ),
+    ];
+    let mut interactor_stderr = interactor.stderr.take().unwrap();
+    let stderr_reader = thread::spawn(move || {
+        let mut message = String::new();
+        let _ = interactor_stderr.read_to_string(&mut message);
+        message
+    });
+
+    let mut solution_status = None;
+    let mut interactor_status = None;
+    let timed_out = loop {
+        if solution_status.is_none() {
+            solution_status = solution.try_wait().ok().flatten();
+        }
+        if interactor_status.is_none() {
+            interactor_status = interactor.try_wait().ok().flatten();
+        }
+        if solution_status.is_some() && interactor_status.is_some() {
+            break false;
+        }
+        if start.elapsed() > time_limit {
+            break true;
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+    let elapsed = start.elapsed();
+
+    if timed_out {
+        let _ = solution.kill();
+        let _ = interactor.kill();
+    }
+    let solution_status = solution_status.or_else(|| solution.wait().ok());
+    let interactor_status = interactor_status.or_else(|| interactor.wait().ok());
+    for relay in relays {
+        let _ = relay.join();
+    }
+    let message = stderr_reader.join().unwrap_or_default();
+
+    let transcript = transcript.lock().unwrap().join("\n");
+    let log = dir.join(format!("{}.log", test.name));
+    if let Err(e) = fs::write(&log, &transcript) {
+        println!(
+            "{} {}: {}",
+            "Couldn't save the transcript to".red(),
+            log.display(),
+            e
+        );
+    }
+
+    if timed_out {
+        return result(Verdict::TimeLimitExceeded, elapsed);
+    }
+    if let Some(status) = interactor_status.filter(|status| !status.success()) {
+        let code = status
+            .code()
+            .map_or("killed".to_string(), |code| format!("exit code {}", code));
+        let message = message.trim();
+        let reason = if message.is_empty() {
+            format!("interactor {}", code)
+        } else {
+            format!("interactor {}: {}", code, message)
+        };
+        return result(Verdict::Rejected { reason, transcript }, elapsed);
+    }
+    match solution_status {
+        Some(status) if !status.success() => {
+            result(Verdict::RuntimeError(status.to_string()), elapsed)
+        }
+        _ => result(Verdict::Accepted, elapsed),
+    }
+}
+
+fn print_result(result: &TestResult) {
+    let verdict = match &result.verdict {
+        Verdict::Accepted => "correct".green(),
+        Verdict::WrongAnswer { .. } => "wrong answer".red(),
+        Verdict::TimeLimitExceeded => "TLE".red(),
+        Verdict::RuntimeError(_) => "runtime error".red(),
+        Verdict::Rejected { .. } => "rejected".red(),
+    };
+    print!(
+        "Test {:<6} {:<16} {:>6} ms",
+        result.name,
+        verdict,
+        result.time.as_millis()
+    );
+    match &result.verdict {
+        Verdict::WrongAnswer { expected, actual } => {
+            if let Some(m) = diff::first_mismatch(expected, actual) {
+                print!(
+                    "   expected {} on line {}, found {}",
+                    m.expected.as_deref().unwrap_or("end of output"),
+                    m.expected_line,
+                    m.actual.as_deref().unwrap_or("end of output")
+                );
+            }
+        }
+        Verdict::RuntimeError(reason) | Verdict::Rejected { reason, .. } => {
+            print!("   {}", reason)
+        }
+        _ => {}
+    }
+    println!();
+}
+
+/// What stays the same between runs of the same tests.
+pub(crate) struct Setup {
+    pub language: String,
+    pub tests: Vec<TestCase>,
+    pub interactor: Option<Executable>,
+}
+
+/// Reads the preferred language, finds the tests and prepares the interactor, printing what went wrong.
+pub(crate) fn setup(tests_dir: &Path, interactor: Option<OsString>) -> Option<Setup> {
+    let language =
+        credential_manager::CredentialManager::global().get::<credential_manager::Language>();
+    let language = match language {
+        Some(language) => language,
+        None => {
+            println!(
+                "{}",
+                "You need to set a preferred language before you run tests".bright_yellow()
+            );
+            return None;
+        }
+    };
+
+    let tests = match discover_tests(tests_dir, interactor.is_some()) {
+        Ok(tests) if tests.is_empty() => {
+            println!(
+                "{}",
+                format!("No tests found in {}", tests_dir.display()).bright_yellow()
+            );
+            return None;
+        }
+        Ok(tests) => tests,
+        Err(e) => {
+            println!("{}", e.red());
+            return None;
+        }
+    };
+
+    let interactor = match interactor.map(|path| interactor_executable(Path::new(&path))) {
+        None => None,
+        Some(Ok(interactor)) => Some(interactor),
+        Some(Err(e)) => {
+            println!("{}\n{}", "Couldn't prepare the interactor".red(), e);
+            return None;
+        }
+    };
+
+    Some(Setup {
+        language,
+        tests,
+        interactor,
+    })
+}
+
+/// Compiles the solution and runs every test, printing a row per test.
+/// Returns `None` when the solution doesn't compile.
+pub(crate) fn run_tests(
+    path: &Path,
+    setup: &Setup,
+    time_limit: Duration,
+) -> Option<Vec<TestResult>> {
+    let built = build_dir(path).and_then(|dir| Ok((compile(path, &setup.language, &dir)?, dir)));
+    let (executable, dir) = match built {
+        Ok(built) => built,
+        Err(e) => {
+            println!("{}\n{}", "Compilation failed".red(), e);
+            return None;
+        }
+    };
+
+    Some(
+        setup
+            .tests
+            .iter()
+            .map(|test| {
+                let result = match &setup.interactor {
+                    Some(interactor) => {
+                        run_interactive(&executable, interactor, test, time_limit, &dir)
+                    }
+                    None => run_one(&executable, test, time_limit),
+                };
+                print_result(&result);
+                result
+            })
+            .collect(),
+    )
+}
+
+/// Prints how many tests passed, returning whether all of them did.
+pub(crate) fn print_summary(results: &[TestResult]) -> bool {
+    let passed = results
+        .iter()
+        .filter(|r| matches!(r.verdict, Verdict::Accepted))
+        .count();
+    let summary = format!("Passed {} of {} tests", passed, results.len());
+    if passed == results.len() {
+        println!("{}", summary.green());
+        true
+    } else {
+        println!("{}", summary.red());
+        false
+    }
+}
+
+pub fn test(
+    path: OsString,
+    tests_dir: PathBuf,
+    time_limit: f64,
+    interactor: Option<OsString>,
+    watch: bool,
+    submit_on_pass: bool,
+) {
+    let time_limit = match Duration::try_from_secs_f64(time_limit) {
+        Ok(limit) if !limit.is_zero() => limit,
+        _ => {
+            println!(
+                "{} {}",
+                "The time limit has to be a positive number of seconds, not".red(),
+                time_limit
+            );
+            return;
+        }
+    };
+    let Some(setup) = setup(&tests_dir, interactor) else {
+        return;
+    };
+    if watch {
+        watch::watch(Path::new(&path), &setup, time_limit, submit_on_pass);
+        return;
+    }
+
+    let Some(results) = run_tests(Path::new(&path), &setup, time_limit) else {
+        return;
+    };
+    if print_summary(&results) {
+        return;
+    }
+
+    let diffs: Vec<String> = results
+        .iter()
+        .filter_map(|r| match &r.verdict {
+            Verdict::WrongAnswer { expected, actual } => Some(format!(
+                "{}\n{}",
+                format!("Test {}", r.name).bold(),
+                diff::unified(expected, actual)
+            )),
+            Verdict::Rejected { reason, transcript } => Some(format!(
+                "{}\n{}\n{}",
+                format!("Test {}", r.name).bold(),
+                reason.red(),
+                transcript
+            )),
+            _ => None,
+        })
+        .collect();
+    if !diffs.is_empty() {
+        pager::page(&diffs.join("\n\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn script(code: &str) -> Executable {
+        Executable {
+            program: "sh".into(),
+            args: vec!["-c".into(), code.into(), "sh".into()],
+        }
+    }
+
+    #[test]
+    fn runs_solutions_against_the_interactor() {
+        let dir = TestDir::new("interactive");
+        let test = TestCase {
+            name: "1".into(),
+            input: dir.write("1.in", "21\n"),
+            output: None,
+        };
+        // Sends the number from the test and expects its double back.
+        let interactor = script(
+            "n=$(cat \"$1\"); echo \"$n\"; read answer; \
+             [ \"$answer\" = $((n * 2)) ] || { echo \"wrong answer $answer\" >&2; exit 1; }",
+        );
+        let time_limit = Duration::from_secs(5);
+        let log = dir.path().join("1.log");
+
+        let correct = run_interactive(
+            &script("read n; echo $((n * 2))"),
+            &interactor,
+            &test,
+            time_limit,
+            dir.path(),
+        );
+        assert!(matches!(correct.verdict, Verdict::Accepted));
+        assert_eq!(fs::read_to_string(&log).unwrap(), "> 21\n< 42");
+
+        let wrong = run_interactive(
+            &script("read n; echo $n"),
+            &interactor,
+            &test,
+            time_limit,
+            dir.path(),
+        );
+        match wrong.verdict {
+            Verdict::Rejected { reason, transcript } => {
+                assert_eq!(reason, "interactor exit code 1: wrong answer 21");
+                assert_eq!(transcript, "> 21\n< 21");
+            }
+            _ => panic!("the interactor should reject a wrong answer"),
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use super::bundle::Bundle;
+use proc_macro2::{Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::visit_mut::{self, VisitMut};
+use syn::{Item, ItemUse, UseTree};
+
+/// A local crate whose items get inlined into the solution as `mod <name> { ... }`.
+pub(crate) struct Library {
+    pub name: String,
+    pub root: PathBuf,
+}
+
+/// Parses `name=path`, where `path` is either the crate directory or its `lib.rs`.
+pub(crate) fn parse_library(spec: &str) -> Result<Library, String> {
+    let (name, path) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=path`, got `{}`", spec))?;
+    let path = PathBuf::from(path);
+    let root = if path.is_dir() {
+        path.join("src").join("lib.rs")
+    } else {
+        path
+    };
+    if !root.is_file() {
+        return Err(format!("{} doesn't exist", root.display()));
+    }
+    Ok(Library {
+        name: name.replace('-', "_"),
+        root,
+    })
+}
+
+fn load(path: &Path, files: &mut Vec<PathBuf>) -> Result<syn::File, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    files.push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+    syn::parse_file(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Replaces `mod foo;` with the contents of `foo.rs` or `foo/mod.rs`, looked up in `dir`.
+fn inline_modules(items: &mut [Item], dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    for item in items {
+        let Item::Mod(module) = item else {
+            continue;
+        };
+        let name = module.ident.to_string();
+        let child_dir = dir.join(&name);
+        match &mut module.content {
+            Some((_, items)) => inline_modules(items, &child_dir, files)?,
+            None => {
+                let path = [dir.join(format!("{}.rs", name)), child_dir.join("mod.rs")]
+                    .into_iter()
+                    .find(|path| path.is_file())
+                    .ok_or_else(|| format!("can't find the file for `mod {};`", name))?;
+                let mut file = load(&path, files)?;
+                inline_modules(&mut file.items, &child_dir, files)?;
+                module.attrs.extend(file.attrs);
+                module.content = Some((Default::default(), file.items));
+                module.semi = None;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Inserts `insert` after every `after ::` in a macro body, where syn can't see paths.
+fn rewrite_macro_tokens(tokens: TokenStream, after: &str, insert: &[TokenTree]) -> TokenStream {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut output = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Group(group) => {
+                let mut rewritten = proc_macro2::Group::new(
+                    group.delimiter(),
+                    rewrite_macro_tokens(group.stream(), after, insert),
+                );
+                rewritten.set_span(group.span());
+                output.push(TokenTree::Group(rewritten));
+            }
+            TokenTree::Ident(ident) if ident == after => {
+                let is_path_start = !matches!(
+                    (i.checked_sub(1).map(|j| &tokens[j]), i.checked_sub(2).map(|j| &tokens[j])),
+                    (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b)))
+                        if a.as_char() == ':' && b.as_char() == ':'
+                );
+                let followed_by_path = matches!(
+                    (tokens.get(i + 1), tokens.get(i + 2)),
+                    (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b)))
+                        if a.as_char() == ':' && b.as_char() == ':'
+                );
+                output.push(tokens[i].clone());
+                if is_path_start && followed_by_path {
+                    output.push(tokens[i + 1].clone());
+                    output.push(tokens[i + 2].clone());
+                    output.extend(insert.iter().cloned());
+                    i += 2;
+                }
+            }
+            token => output.push(token.clone()),
+        }
+        i += 1;
+    }
+    output.into_iter().collect()
+}
+
+fn path_separator() -> [TokenTree; 2] {
+    [
+        TokenTree::Punct(Punct::new(':', Spacing::Joint)),
+        TokenTree::Punct(Punct::new(':', Spacing::Alone)),
+    ]
+}
+
+/// Makes the solution refer to `lib::...` as `crate::lib::...`, so the paths also
+/// resolve from inside its modules.
+struct PrefixWithCrate<'a> {
+    library: &'a str,
+}
+
+impl VisitMut for PrefixWithCrate<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.leading_colon.is_none()
+            && path.segments.len() >= 2
+            && path.segments[0].ident == self.library
+        {
+            path.segments
+                .insert(0, Ident::new("crate", Span::call_site()).into());
+        }
+        visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_item_use_mut(&mut self, item: &mut ItemUse) {
+        if item.leading_colon.is_none()
+            && matches!(&item.tree, UseTree::Path(p) if p.ident == self.library)
+        {
+            let tree = std::mem::replace(
+                &mut item.tree,
+                UseTree::Glob(syn::UseGlob {
+                    star_token: Default::default(),
+                }),
+            );
+            item.tree = UseTree::Path(syn::UsePath {
+                ident: Ident::new("crate", Span::call_site()),
+                colon2_token: Default::default(),
+                tree: Box::new(tree),
+            });
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        mac.tokens = prefix_library_in_tokens(mac.tokens.clone(), self.library);
+    }
+}
+
+/// `lib::x` inside macro calls, e.g. `println!("{}", lib::x())`.
+fn prefix_library_in_tokens(tokens: TokenStream, library: &str) -> TokenStream {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let mut output = Vec::with_capacity(tokens.len());
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Group(group) => {
+                let mut rewritten = proc_macro2::Group::new(
+                    group.delimiter(),
+                    prefix_library_in_tokens(group.stream(), library),
+                );
+                rewritten.set_span(group.span());
+                output.push(TokenTree::Group(rewritten));
+            }
+            TokenTree::Ident(ident) if ident == library => {
+                let after_separator =
+                    i >= 1 && matches!(&tokens[i - 1], TokenTree::Punct(p) if p.as_char() == ':');
+                let before_separator = matches!(
+                    tokens.get(i + 1),
+                    Some(TokenTree::Punct(p)) if p.as_char() == ':'
+                );
+                if !after_separator && before_separator {
+                    output.push(TokenTree::Ident(Ident::new("crate", Span::call_site())));
+                    output.extend(path_separator());
+                }
+                output.push(token.clone());
+            }
+            token => output.push(token.clone()),
+        }
+    }
+    output.into_iter().collect()
+}
+
+/// Makes the library's `crate::...` paths point into the module it's inlined as.
+struct Reroot<'a> {
+    library: &'a str,
+}
+
+impl VisitMut for Reroot<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.segments.len() >= 2 && path.segments[0].ident == "crate" {
+            path.segments
+                .insert(1, Ident::new(self.library, Span::call_site()).into());
+        }
+        visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_use_tree_mut(&mut self, tree: &mut UseTree) {
+        if let UseTree::Path(path) = tree
+            && path.ident == "crate"
+        {
+            let inner = std::mem::replace(
+                &mut *path.tree,
+                UseTree::Glob(syn::UseGlob {
+                    star_token: Default::default(),
+                }),
+            );
+            *path.tree = UseTree::Path(syn::UsePath {
+                ident: Ident::new(self.library, Span::call_site()),
+                colon2_token: Default::default(),
+                tree: Box::new(inner),
+            });
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        let mut insert = vec![TokenTree::Ident(Ident::new(
+            self.library,
+            Span::call_site(),
+        ))];
+        insert.extend(path_separator());
+        mac.tokens = rewrite_macro_tokens(mac.tokens.clone(), "crate", &insert);
+    }
+}
+
+fn count_idents(tokens: TokenStream, counts: &mut HashMap<String, usize>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => *counts.entry(ident.to_string()).or_default() += 1,
+            TokenTree::Group(group) => count_idents(group.stream(), counts),
+            _ => {}
+        }
+    }
+}
+
+/// The name an item defines.
+fn item_name(item: &Item) -> Option<String> {
+    let ident = match item {
+        Item::Fn(item) => &item.sig.ident,
+        Item::Struct(item) => &item.ident,
+        Item::Enum(item) => &item.ident,
+        Item::Union(item) => &item.ident,
+        Item::Const(item) => &item.ident,
+        Item::Static(item) => &item.ident,
+        Item::Type(item) => &item.ident,
+        Item::Trait(item) => &item.ident,
+        Item::Macro(item) => item.ident.as_ref()?,
+        _ => return None,
+    };
+    Some(ident.to_string())
+}
+
+/// The names an `impl` block depends on: the type it's for and the trait it implements, if any.
+fn impl_names(item: &syn::ItemImpl) -> Vec<String> {
+    let mut names = vec![];
+    if let syn::Type::Path(path) = &*item.self_ty
+        && let Some(segment) = path.path.segments.last()
+    {
+        names.push(segment.ident.to_string());
+    }
+    if let Some((_, path, _)) = &item.trait_
+        && let Some(segment) = path.segments.last()
+    {
+        names.push(segment.ident.to_string());
+    }
+    names
+}
+
+/// The names of the items the library defines, `impl` blocks aside.
+fn defined_names(items: &[Item], names: &mut HashSet<String>) {
+    for item in items {
+        match item {
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    defined_names(items, names);
+                }
+            }
+            item => names.extend(item_name(item)),
+        }
+    }
+}
+
+fn is_test_only(item: &Item) -> bool {
+    let attrs = match item {
+        Item::Mod(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        _ => return false,
+    };
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg") && attr.parse_args::<Ident>().is_ok_and(|arg| arg == "test")
+    })
+}
+
+/// Counts identifiers in the library. `use` declarations don't count as usages,
+/// except the original names of renamed imports.
+fn count_library(items: &[Item], counts: &mut HashMap<String, usize>) {
+    for item in items {
+        match item {
+            Item::Use(item) => count_renames(&item.tree, counts),
+            Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    count_library(items, counts);
+                }
+            }
+            item => count_idents(item.to_token_stream(), counts),
+        }
+    }
+}
+
+fn count_renames(tree: &UseTree, counts: &mut HashMap<String, usize>) {
+    match tree {
+        UseTree::Path(path) => count_renames(&path.tree, counts),
+        UseTree::Rename(rename) => *counts.entry(rename.ident.to_string()).or_default() += 1,
+        UseTree::Group(group) => group.items.iter().for_each(|t| count_renames(t, counts)),
+        _ => {}
+    }
+}
+
+/// How often each name shows up inside its own definition and `impl` blocks.
+fn count_self_references(items: &[Item], internal: &mut HashMap<String, usize>) {
+    for item in items {
+        if let Item::Mod(module) = item {
+            if let Some((_, items)) = &module.content {
+                count_self_references(items, internal);
+            }
+            continue;
+        }
+        let names = match item {
+            Item::Impl(item) => impl_names(item),
+            item => item_name(item).into_iter().collect(),
+        };
+        let mut own = HashMap::new();
+        count_idents(item.to_token_stream(), &mut own);
+        for name in names {
+            *internal.entry(name.clone()).or_default() += own.get(&name).copied().unwrap_or(0);
+        }
+    }
+}
+
+/// Removes the unused items. `impl` blocks go only with the library type or trait they're for,
+/// so a kept trait keeps its impls for foreign types such as `impl Gcd for u64`.
+fn remove_unused(
+    items: &mut Vec<Item>,
+    used: &dyn Fn(&str) -> bool,
+    defined: &HashSet<String>,
+) -> bool {
+    let before = items.len();
+    items.retain(|item| {
+        !is_test_only(item)
+            && match item {
+                Item::Impl(item) => impl_names(item)
+                    .iter()
+                    .all(|name| !defined.contains(name) || used(name)),
+                item => item_name(item).is_none_or(|name| used(&name)),
+            }
+    });
+    let mut changed = items.len() != before;
+    for item in items.iter_mut() {
+        if let Item::Mod(module) = item
+            && let Some((_, items)) = &mut module.content
+        {
+            changed |= remove_unused(items, used, defined);
+        }
+    }
+    changed
+}
+
+/// Drops imported names that aren't used anymore, so imports of removed items don't dangle.
+fn prune_use_tree(tree: &mut UseTree, used: &dyn Fn(&str) -> bool) -> bool {
+    match tree {
+        UseTree::Path(path) => prune_use_tree(&mut path.tree, used),
+        UseTree::Name(name) => name.ident == "self" || used(&name.ident.to_string()),
+        UseTree::Rename(rename) => used(&rename.rename.to_string()),
+        UseTree::Glob(_) => true,
+        UseTree::Group(group) => {
+            let items = std::mem::take(&mut group.items);
+            group.items = items
+                .into_iter()
+                .filter_map(|mut tree| prune_use_tree(&mut tree, used).then_some(tree))
+                .collect();
+            !group.items.is_empty()
+        }
+    }
+}
+
+fn prune_imports(items: &mut Vec<Item>, used: &dyn Fn(&str) -> bool) {
+    items.retain_mut(|item| match item {
+        Item::Use(item) => prune_use_tree(&mut item.tree, used),
+        Item::Mod(module) => {
+            if let Some((_, items)) = &mut module.content {
+                prune_imports(items, used);
+            }
+            true
+        }
+        _ => true,
+    });
+}
+
+/// Removes library items whose names are never mentioned outside their own definition and
+/// `impl` blocks. Names are matched textually, so an item that is only reached through a glob
+/// import like `use algo::*` is removed too.
+fn strip_unused(file: &mut syn::File, library: &str) {
+    let is_library = |item: &Item| matches!(item, Item::Mod(module) if module.ident == library);
+
+    loop {
+        let mut counts = HashMap::new();
+        let mut internal = HashMap::new();
+        for item in &file.items {
+            match item {
+                Item::Mod(module) if is_library(item) => {
+                    let items = &module.content.as_ref().unwrap().1;
+                    count_library(items, &mut counts);
+                    count_self_references(items, &mut internal);
+                }
+                item => count_idents(item.to_token_stream(), &mut counts),
+            }
+        }
+
+        let used = |name: &str| {
+            counts.get(name).copied().unwrap_or(0) > internal.get(name).copied().unwrap_or(0)
+        };
+        let library_items = file.items.iter_mut().find(|item| is_library(item));
+        let Some(Item::Mod(module)) = library_items else {
+            return;
+        };
+        let items = &mut module.content.as_mut().unwrap().1;
+        let mut defined = HashSet::new();
+        defined_names(items, &mut defined);
+        if !remove_unused(items, &used, &defined) {
+            let mentioned = |name: &str| counts.get(name).copied().unwrap_or(0) > 0;
+            prune_imports(items, &mentioned);
+            return;
+        }
+    }
+}
+
+/// Inlines the solution's `mod` files and the given local crates into a single file.
+/// Libraries become top level modules, unused library items are stripped.
+pub(crate) fn bundle_rust(path: &Path, libraries: &[Library]) -> Result<Bundle, String> {
+    let mut files = vec![];
+    let mut main = load(path, &mut files)?;
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    inline_modules(&mut main.items, &dir, &mut files)?;
+
+    for library in libraries {
+        let name = library.name.as_str();
+        main.items
+            .retain(|item| !matches!(item, Item::ExternCrate(krate) if krate.ident == name));
+        PrefixWithCrate { library: name }.visit_file_mut(&mut main);
+
+        let mut lib = load(&library.root, &mut files)?;
+        let lib_dir = library
+            .root
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        inline_modules(&mut lib.items, &lib_dir, &mut files)?;
+        Reroot { library: name }.visit_file_mut(&mut lib);
+
+        let module: syn::ItemMod = syn::parse_quote! {
+            #[allow(dead_code, unused_imports)]
+            pub mod __library {}
+        };
+        let mut module = module;
+        module.ident = Ident::new(name, Span::call_site());
+        module.attrs.extend(
+            lib.attrs
+                .into_iter()
+                .filter(|attr| attr.path().is_ident("allow")),
+        );
+        module.content = Some((Default::default(), lib.items));
+        main.items.push(Item::Mod(module));
+        strip_unused(&mut main, name);
+    }
+
+    Ok(Bundle {
+        source: prettyplease::unparse(&main),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn inlines_modules_and_library() {
+        let dir = TestDir::new("bundle-rust");
+        dir.write(
+            "algo/src/lib.rs",
+            "pub mod dsu;\npub fn unused() {}\npub use dsu::Dsu;\n",
+        );
+        dir.write("algo/src/dsu.rs",
+            "use crate::helper;\npub struct Dsu;\nimpl Dsu { pub fn new() -> Self { helper(); Dsu } }\npub struct Fenwick;\nimpl Fenwick {}\n",
+        );
+        let lib = fs::read_to_string(dir.path().join("algo/src/lib.rs")).unwrap();
+        dir.write("algo/src/lib.rs", &(lib + "pub fn helper() {}\n"));
+        dir.write("sol/io.rs", "pub fn read() -> u32 { 1 }\n");
+        let main = dir.write("sol/main.rs",
+            "mod io;\nuse algo::Dsu;\nfn main() { let _ = Dsu::new(); println!(\"{}\", io::read()); }\n",
+        );
+
+        let library =
+            parse_library(&format!("algo={}", dir.path().join("algo").display())).unwrap();
+        let bundle = bundle_rust(&main, &[library]).unwrap();
+        let source = bundle.source;
+        assert_eq!(bundle.files.len(), 4);
+        assert!(source.contains("mod io {"));
+        assert!(source.contains("pub mod algo {"));
+        assert!(source.contains("use crate::algo::Dsu;"));
+        assert!(source.contains("use crate::algo::helper;"));
+        assert!(source.contains("pub struct Dsu"));
+        assert!(!source.contains("Fenwick"));
+        assert!(!source.contains("fn unused"));
+        syn::parse_file(&source).unwrap();
+    }
+
+    #[test]
+    fn keeps_trait_impls_for_foreign_types() {
+        let dir = TestDir::new("bundle-rust-traits");
+        dir.write(
+            "algo/src/lib.rs",
+            "pub trait Gcd { fn gcd(self, other: Self) -> Self; }\n\
+             impl Gcd for u64 { fn gcd(self, other: u64) -> u64 { if other == 0 { self } else { other.gcd(self % other) } } }\n\
+             pub trait Total { fn total(&self) -> u64; }\n\
+             impl Total for Vec<u64> { fn total(&self) -> u64 { self.iter().sum() } }\n\
+             pub struct Unused;\n\
+             impl Unused { pub fn new() -> Self { Unused } }\n\
+             impl Total for Unused { fn total(&self) -> u64 { 0 } }\n",
+        );
+        let main = dir.write(
+            "main.rs",
+            "use algo::{Gcd, Total};\nfn main() { println!(\"{} {}\", 12u64.gcd(8), vec![1u64, 2].total()); }\n",
+        );
+
+        let library =
+            parse_library(&format!("algo={}", dir.path().join("algo").display())).unwrap();
+        let source = bundle_rust(&main, &[library]).unwrap().source;
+        assert!(source.contains("impl Gcd for u64"));
+        assert!(source.contains("impl Total for Vec<u64>"));
+        assert!(!source.contains("Unused"));
+        syn::parse_file(&source).unwrap();
+    }
+}
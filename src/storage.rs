@@ -0,0 +1,46 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Where state that doesn't fit in the keyring lives, e.g. `~/.local/share/kilo-cli`.
+/// `KILO_CLI_DATA_DIR` overrides it.
+pub(crate) fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("KILO_CLI_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    let home = || env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+    } else if cfg!(target_os = "macos") {
+        home().join("Library").join("Application Support")
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home().join(".local").join("share"))
+    };
+    base.join("kilo-cli")
+}
+
+/// Reads `name` from the data directory, falling back to the default when it doesn't exist yet.
+pub(crate) fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T, String> {
+    let path = data_dir().join(name);
+    match fs::read_to_string(&path) {
+        Ok(content) => {
+            serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+pub(crate) fn save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let path = data_dir().join(name);
+    fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| format!("{}: {}", path.display(), e))
+}
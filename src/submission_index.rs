@@ -0,0 +1,96 @@
+use super::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const FILE: &str = "submissions.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Entry {
+    pub hash: String,
+    pub id: u64,
+    #[serde(default)]
+    pub score: Option<f64>,
+}
+
+/// Hashes of every source submitted, per problem id.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct SubmissionIndex {
+    #[serde(flatten)]
+    problems: HashMap<String, Vec<Entry>>,
+}
+
+/// FNV-1a over the content with all whitespace removed, so reformatting
+/// a file doesn't make it look new.
+pub(crate) fn content_hash(content: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in content.iter().filter(|b| !b.is_ascii_whitespace()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+impl SubmissionIndex {
+    pub(crate) fn load() -> Result<Self, String> {
+        storage::load(FILE)
+    }
+
+    pub(crate) fn save(&self) -> Result<(), String> {
+        storage::save(FILE, self)
+    }
+
+    pub(crate) fn find(&self, problem: &str, hash: &str) -> Option<&Entry> {
+        self.problems
+            .get(problem)?
+            .iter()
+            .rev()
+            .find(|entry| entry.hash == hash)
+    }
+
+    pub(crate) fn record(&mut self, problem: &str, hash: String, id: u64) {
+        self.problems
+            .entry(problem.to_string())
+            .or_default()
+            .push(Entry {
+                hash,
+                id,
+                score: None,
+            });
+    }
+
+    pub(crate) fn set_score(&mut self, id: u64, score: f64) {
+        let entry = self
+            .problems
+            .values_mut()
+            .flatten()
+            .find(|entry| entry.id == id);
+        if let Some(entry) = entry {
+            entry.score = Some(score);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_ignores_whitespace() {
+        assert_eq!(
+            content_hash(b"int main() {\n    return 0;\n}\n"),
+            content_hash(b"int main(){return 0;}")
+        );
+        assert_ne!(content_hash(b"return 0;"), content_hash(b"return 1;"));
+    }
+
+    #[test]
+    fn finds_previous_submission() {
+        let mut index = SubmissionIndex::default();
+        index.record("42", content_hash(b"a b"), 7);
+        index.set_score(7, 55.0);
+        let entry = index.find("42", &content_hash(b"ab")).unwrap();
+        assert_eq!(entry.id, 7);
+        assert_eq!(entry.score, Some(55.0));
+        assert!(index.find("43", &content_hash(b"ab")).is_none());
+    }
+}
@@ -1,11 +1,15 @@
-use super::{credential_manager, waiter};
+use super::config::{self, Config};
+use super::hooks::{self, HookContext};
+use super::submission_index::{self, SubmissionIndex};
+use super::{bundle, checks, credential_manager, history, output_only, problem, queue, waiter};
 use colored::Colorize;
 use serde::Deserialize;
-use serde_json;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::ffi::OsString;
-use std::path::PathBuf;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::Duration;
 
@@ -38,7 +42,7 @@ struct TestInfo {
 }
 
 fn get_status(test: &TestInfo) -> String {
-    if test.skipped == true {
+    if test.skipped {
         return "skipped".to_string();
     }
 
@@ -58,7 +62,7 @@ enum Range {
     Double(u16, u16),
 }
 
-fn split_in_ranges(v: &Vec<u16>) -> Vec<Range> {
+fn split_in_ranges(v: &[u16]) -> Vec<Range> {
     if v.is_empty() {
         return Vec::<Range>::new();
     }
@@ -95,7 +99,7 @@ fn print_result(json: ApiResponse) {
                 .entry("MLE".to_string())
                 .or_insert(vec![])
                 .push(t.visible_id);
-        } else if t.skipped == true {
+        } else if t.skipped {
             hashmap
                 .entry("skipped".to_string())
                 .or_insert(vec![])
@@ -118,7 +122,7 @@ fn print_result(json: ApiResponse) {
     ];
 
     for verdict in verdicts {
-        let ranges = split_in_ranges(hashmap.entry((&verdict).parse().unwrap()).or_insert(vec![]));
+        let ranges = split_in_ranges(hashmap.entry(verdict.parse().unwrap()).or_insert(vec![]));
         if ranges.is_empty() {
             continue;
         }
@@ -152,15 +156,152 @@ fn print_result(json: ApiResponse) {
     }
 }
 
-pub fn submit(path: OsString) {
-    let problem =
-        credential_manager::CredentialManager::global().get::<credential_manager::Cache>();
-    let problem = match problem {
-        Some(p) => p,
-        None => {
+/// Output-only problems are submitted as one archive with every `NN.out` file in `dir`.
+/// The outputs are checked against the problem's tests, `force` submits them when the
+/// tests can't be listed.
+fn output_only_archive(problem: &str, dir: &Path, force: bool) -> Result<Vec<u8>, String> {
+    let outputs = output_only::collect(dir)?;
+    if outputs.is_empty() {
+        return Err(format!("No NN.out files found in {}", dir.display()));
+    }
+    match problem::test_ids(&reqwest::blocking::Client::new(), problem) {
+        Ok(tests) => output_only::validate(&outputs, &tests)?,
+        Err(e) if force => println!(
+            "{} {}",
+            "Couldn't check the number of tests, submitting anyway :".yellow(),
+            e
+        ),
+        Err(e) => {
+            return Err(format!(
+                "couldn't check the outputs against the problem's tests ({}), use --force to submit them unchecked",
+                e
+            ));
+        }
+    }
+    output_only::archive(&outputs)
+}
+
+pub struct SubmitOptions {
+    pub bundle: bool,
+    pub include_dirs: Vec<PathBuf>,
+    pub libraries: Vec<String>,
+    pub force: bool,
+    pub skip_checks: bool,
+    pub queue: bool,
+    /// A problem id or `@n` from the history, the last viewed problem when missing.
+    pub problem: Option<String>,
+}
+
+/// What gets uploaded as the `code` part.
+pub(crate) struct Payload {
+    pub content: Vec<u8>,
+    pub file_name: String,
+    pub mime: String,
+}
+
+fn build_payload(
+    problem: &str,
+    file_path: &Path,
+    output_only: bool,
+    options: &SubmitOptions,
+) -> Result<Payload, String> {
+    let file_name = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "main".to_string());
+
+    if output_only {
+        let archive = output_only_archive(problem, file_path, options.force)
+            .map_err(|e| format!("Couldn't package your outputs : {}", e))?;
+        return Ok(Payload {
+            content: archive,
+            file_name: "outputs.zip".to_string(),
+            mime: "application/zip".to_string(),
+        });
+    }
+
+    let content = if options.bundle {
+        let bundle = bundle::bundle_file(file_path, &options.include_dirs, &options.libraries)
+            .map_err(|e| format!("Couldn't bundle your solution : {}", e))?;
+        println!("Bundled {} files", bundle.files.len());
+        bundle.source.into_bytes()
+    } else {
+        fs::read(file_path).map_err(|e| format!("Could not open source file : {}", e))?
+    };
+    Ok(Payload {
+        content,
+        file_name,
+        mime: "text/plain".to_string(),
+    })
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y")
+}
+
+/// Warns about sources already submitted to this problem. Returns whether to go on.
+fn check_duplicate(index: &SubmissionIndex, problem: &str, hash: &str, force: bool) -> bool {
+    let Some(previous) = index.find(problem, hash) else {
+        return true;
+    };
+    let score = previous
+        .score
+        .map_or("not judged yet".to_string(), |score| {
+            format!("score {}", score.floor())
+        });
+    println!(
+        "{}",
+        format!(
+            "You already submitted this code to problem {} as submission #{} ({})",
+            problem, previous.id, score
+        )
+        .bright_yellow()
+    );
+    force || confirm("Submit it again?")
+}
+
+/// Runs the configured pre-submit checks, printing a report when any of them fails.
+fn passes_checks(config: &Config, problem: &str, payload: &Payload, language: &str) -> bool {
+    if !config.checks.enabled {
+        return true;
+    }
+
+    let details = problem::details(&reqwest::blocking::Client::new(), problem).ok();
+    let source = String::from_utf8_lossy(&payload.content);
+    let failures = checks::run(&config.checks, &source, language, details.as_ref());
+    if failures.is_empty() {
+        return true;
+    }
+
+    println!("{}", "Pre-submit checks failed:".red());
+    for failure in failures {
+        println!(
+            "  {} {}",
+            format!("{}:", failure.check).red(),
+            failure.message
+        );
+    }
+    println!(
+        "{}",
+        "Fix them, or use --skip-checks to submit anyway".bright_yellow()
+    );
+    false
+}
+
+pub fn submit(path: OsString, options: SubmitOptions) {
+    let problem = match history::resolve(options.problem.as_deref()) {
+        Ok(p) => p,
+        Err(_) if options.problem.is_none() => {
             println!("{}", "Submissions are made to the last seen problem. You need to look at a problem first.".bright_yellow());
             return;
         }
+        Err(e) => {
+            println!("{}", e.bright_yellow());
+            return;
+        }
     };
 
     let token = credential_manager::CredentialManager::global().get::<credential_manager::Token>();
@@ -188,16 +329,165 @@ pub fn submit(path: OsString) {
         }
     };
 
-    let spinner = waiter::Waiter::start();
     let file_path: PathBuf = path.into();
+    let file_name = file_path.file_name().and_then(|name| name.to_str());
+    let is_grader = file_name.is_some_and(|name| {
+        // Only headers need the attachment list, they're graders when the problem ships them.
+        let attachments = if name.ends_with(".h") {
+            problem::attachments(&reqwest::blocking::Client::new(), &problem).unwrap_or_default()
+        } else {
+            vec![]
+        };
+        problem::is_grader_file(name, &attachments)
+    });
+    if is_grader {
+        println!(
+            "{}",
+            "That's the problem's grader. Submit the file with your implementation instead."
+                .bright_yellow()
+        );
+        return;
+    }
+
+    let output_only = language == "outputOnly" || file_path.is_dir();
+    let config = config::load().unwrap_or_else(|e| {
+        println!("{} {}", "Couldn't read the configuration :".yellow(), e);
+        Config::default()
+    });
+    let hook_context = HookContext {
+        problem: &problem,
+        source: &file_path,
+        language: if output_only { "outputOnly" } else { &language },
+    };
+    if let Err(e) = hooks::pre_submit(&config.hooks, &hook_context) {
+        println!(
+            "{} {}",
+            "Submission cancelled by a pre-submit hook :".red(),
+            e
+        );
+        return;
+    }
+
+    let payload = match build_payload(&problem, &file_path, output_only, &options) {
+        Ok(payload) => payload,
+        Err(e) => {
+            println!("{}", e.red());
+            return;
+        }
+    };
+    let language = hook_context.language;
+
+    if !output_only && !options.skip_checks && !passes_checks(&config, &problem, &payload, language)
+    {
+        return;
+    }
+
+    let mut index = SubmissionIndex::load().unwrap_or_else(|e| {
+        println!("{} {}", "Couldn't read the submission index :".yellow(), e);
+        SubmissionIndex::default()
+    });
+    let hash = submission_index::content_hash(&payload.content);
+    if !check_duplicate(&index, &problem, &hash, options.force) {
+        return;
+    }
+
+    if options.queue {
+        enqueue(&problem, language, &file_path, payload, hash);
+        return;
+    }
+
+    let submission_id = match upload(&token, &problem, language, &payload) {
+        Ok(id) => id,
+        Err(UploadError::Network(e)) => {
+            println!("{} {}", "Couldn't submit your code : ".red(), e);
+            enqueue(&problem, language, &file_path, payload, hash);
+            return;
+        }
+        Err(UploadError::Unknown(e)) => {
+            println!("{} {}", "Couldn't confirm the submission : ".red(), e);
+            println!(
+                "{}",
+                "It may have gone through, check your submissions on Kilonova before sending it again."
+                    .bright_yellow()
+            );
+            return;
+        }
+        Err(UploadError::Unavailable(e) | UploadError::Rejected(e)) => {
+            println!("{} {}", "Couldn't submit your code : ".red(), e);
+            return;
+        }
+    };
+
+    index.record(&problem, hash, submission_id);
+    save_index(&index);
+
+    println!("{}", "Submitted your code. Judging...".green());
+    judge(submission_id, &mut index, &config, &hook_context);
+}
+
+fn enqueue(problem: &str, language: &str, source: &Path, payload: Payload, hash: String) {
+    match queue::push(problem, language, source, payload, hash) {
+        Ok(position) => println!(
+            "{}",
+            format!(
+                "Saved in the offline queue at position {}. Run `queue flush` once you're back online.",
+                position
+            )
+            .bright_yellow()
+        ),
+        Err(e) => println!("{} {}", "Couldn't queue your code : ".red(), e),
+    }
+}
+
+pub(crate) fn save_index(index: &SubmissionIndex) {
+    if let Err(e) = index.save() {
+        println!(
+            "{} {}",
+            "Couldn't update the submission index :".yellow(),
+            e
+        );
+    }
+}
+
+pub(crate) enum UploadError {
+    /// Kilonova couldn't be reached, worth retrying later.
+    Network(String),
+    /// The upload was sent but no answer came back, it may or may not have been submitted.
+    Unknown(String),
+    /// Kilonova refused it for now: an expired login or a server error.
+    Unavailable(String),
+    /// Kilonova refused the submission itself, sending it again won't help.
+    Rejected(String),
+}
+
+/// Whether a failed upload may work later. Expired logins, rate limits and server errors
+/// can go away, any other 4xx is about the submission itself.
+fn is_temporary(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || matches!(
+            status,
+            reqwest::StatusCode::UNAUTHORIZED
+                | reqwest::StatusCode::FORBIDDEN
+                | reqwest::StatusCode::TOO_MANY_REQUESTS
+        )
+}
+
+/// Uploads the payload, returning the id of the new submission.
+pub(crate) fn upload(
+    token: &str,
+    problem: &str,
+    language: &str,
+    payload: &Payload,
+) -> Result<u64, UploadError> {
+    let spinner = waiter::Waiter::start();
     let form = reqwest::blocking::multipart::Form::new()
-        .text("problem_id", problem)
-        .text("language", language)
+        .text("problem_id", problem.to_string())
+        .text("language", language.to_string())
         .part(
             "code",
-            reqwest::blocking::multipart::Part::file(&file_path)
-                .expect("Could not open source file")
-                .mime_str("text/plain")
+            reqwest::blocking::multipart::Part::bytes(payload.content.clone())
+                .file_name(payload.file_name.clone())
+                .mime_str(&payload.mime)
                 .expect("Invalid MIME"),
         );
 
@@ -211,28 +501,36 @@ pub fn submit(path: OsString) {
     spinner.stop();
     let response = match response {
         Ok(r) => r,
-        Err(e) => {
-            println!("{} {}", "Couldn't submit your code : ".red(), e);
-            return;
-        }
+        Err(e) if e.is_connect() => return Err(UploadError::Network(e.to_string())),
+        // The server may have received it before the answer timed out.
+        Err(e) if e.is_timeout() => return Err(UploadError::Unknown(e.to_string())),
+        Err(e) => return Err(UploadError::Rejected(e.to_string())),
     };
 
-    if response.status() != reqwest::StatusCode::OK {
-        println!(
-            "{} {}",
-            "Couldn't submit your code : ".red(),
-            response.text().unwrap()
-        );
-        return;
+    let status = response.status();
+    if status != reqwest::StatusCode::OK {
+        let message = format!("{} {}", status, response.text().unwrap_or_default());
+        return Err(if is_temporary(status) {
+            UploadError::Unavailable(message)
+        } else {
+            UploadError::Rejected(message)
+        });
     }
 
-    let json: Value = serde_json::from_str(&response.text().unwrap())
-        .expect("Couldn't parse the response from the server");
-    let submission_id = json["data"].as_u64().unwrap();
-    let submission_id = submission_id.to_string();
-
-    println!("{}", "Submitted your code. Judging...".green());
+    let json: Value = serde_json::from_str(&response.text().unwrap_or_default())
+        .map_err(|_| UploadError::Unknown("Couldn't parse the response from the server".into()))?;
+    json["data"]
+        .as_u64()
+        .ok_or_else(|| UploadError::Rejected(json["data"].to_string()))
+}
 
+/// Waits for the verdict and prints it, then records the score and runs the post-verdict hooks.
+pub(crate) fn judge(
+    submission_id: u64,
+    index: &mut SubmissionIndex,
+    config: &Config,
+    hook_context: &HookContext,
+) {
     let spinner = waiter::Waiter::start();
     const POLL_INTERVAL: Duration = Duration::from_secs(5);
     let api_url = format!(
@@ -244,13 +542,16 @@ pub fn submit(path: OsString) {
         thread::sleep(POLL_INTERVAL);
         match reqwest::blocking::Client::new().get(&api_url).send() {
             Ok(response) if response.status() == reqwest::StatusCode::OK => {
-                let json = response.text().unwrap();
-                let json: ApiResponse = serde_json::from_str(&json)
+                let raw = response.text().unwrap();
+                let json: ApiResponse = serde_json::from_str(&raw)
                     .expect("Couldn't parse the response from the server");
                 let status: &str = json.data.status.as_ref();
                 if status == "finished" {
                     spinner.stop();
+                    index.set_score(submission_id, json.data.score);
+                    save_index(index);
                     print_result(json);
+                    hooks::post_verdict(&config.hooks, hook_context, submission_id, &raw);
                     break;
                 }
             }
@@ -265,3 +566,17 @@ pub fn submit(path: OsString) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn keeps_retryable_upload_failures() {
+        assert!(is_temporary(StatusCode::UNAUTHORIZED));
+        assert!(is_temporary(StatusCode::BAD_GATEWAY));
+        assert!(!is_temporary(StatusCode::BAD_REQUEST));
+        assert!(!is_temporary(StatusCode::UNPROCESSABLE_ENTITY));
+    }
+}
@@ -37,7 +37,7 @@ pub fn get() {
     let name = get_user();
     let print = match name {
         Ok(name) => format!("Logged in as {}", name),
-        Err(e) => format!("Error : {}", e.to_string()),
+        Err(e) => format!("Error : {}", e),
     };
 
     spinner.stop();
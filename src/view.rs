@@ -7,24 +7,23 @@ use crossterm::{
 };
 use std::io::stdout;
 
-pub fn view_latest_statement() {
-    let problem_id =
-        credential_manager::CredentialManager::global().get::<credential_manager::Cache>();
+/// Shows the statement of `problem`, an id or `@n` from the history, by default the last viewed one.
+pub fn view_latest_statement(problem: Option<&str>, both: bool) {
+    match history::resolve(problem) {
+        Ok(id) => show(&id, both),
+        Err(e) => println!("{}", e.yellow()),
+    }
+}
 
-    if let Some(id) = problem_id {
-        execute!(stdout(), Clear(ClearType::All), cursor::MoveTo(0, 0)).unwrap();
+/// Opens a problem's statement on a clean screen, restoring the terminal afterwards.
+pub(crate) fn show(id: &str, both: bool) {
+    execute!(stdout(), Clear(ClearType::All), cursor::MoveTo(0, 0)).unwrap();
 
-        disable_raw_mode().unwrap();
-        execute!(stdout(), cursor::Show).unwrap();
+    disable_raw_mode().unwrap();
+    execute!(stdout(), cursor::Show).unwrap();
 
-        browser::display_problem(&id);
+    browser::display_problem(id, both);
 
-        enable_raw_mode().unwrap();
-        execute!(stdout(), cursor::Hide).unwrap();
-    } else {
-        println!(
-            "{}",
-            "You need to look at a problem before doing this".yellow()
-        );
-    }
+    enable_raw_mode().unwrap();
+    execute!(stdout(), cursor::Hide).unwrap();
 }
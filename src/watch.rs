@@ -0,0 +1,124 @@
+use super::runner::{self, Setup};
+use super::{bundle, problem, submitter};
+use colored::Colorize;
+use crossterm::cursor;
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+use std::fs;
+use std::io::stdout;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+/// Editors often write a file in several steps, give them time to finish.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// The solution and the local headers or modules it pulls in.
+fn sources(path: &Path) -> Vec<PathBuf> {
+    let bundled = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["cpp", "cc", "cxx", "rs"].contains(&ext));
+    let files = if bundled {
+        bundle::bundle_file(path, &[], &[])
+            .map(|bundle| bundle.files)
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    if files.is_empty() {
+        vec![path.to_path_buf()]
+    } else {
+        files
+    }
+}
+
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| fs::metadata(file).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn redraw_header(path: &Path, files: usize) {
+    let _ = execute!(stdout(), cursor::MoveTo(0, 0), Clear(ClearType::All));
+    println!(
+        "{} {} ({} {})",
+        "Watching".bold(),
+        path.display(),
+        files,
+        if files == 1 { "file" } else { "files" }
+    );
+    println!();
+}
+
+/// Reruns the tests every time the solution, one of its headers or the grader changes.
+/// With `submit_on_pass`, the solution is submitted and watching stops once every test passes.
+pub(crate) fn watch(path: &Path, setup: &Setup, time_limit: Duration, submit_on_pass: bool) {
+    let grader_dir = path.parent().unwrap_or(Path::new("."));
+    let watched = || {
+        let sources = sources(path);
+        let mut files = sources.clone();
+        files.extend(problem::grader_sources(grader_dir));
+        (sources, files)
+    };
+    // Finding the headers means parsing the sources, only redo it once something changed.
+    let (mut sources, mut files) = watched();
+    let mut last_seen = None;
+    loop {
+        let times = modification_times(&files);
+        if last_seen.as_ref() == Some(&times) {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        thread::sleep(SETTLE_TIME);
+        if last_seen.is_some() {
+            (sources, files) = watched();
+        }
+        last_seen = Some(modification_times(&files));
+
+        redraw_header(path, files.len());
+        let passed = runner::run_tests(path, setup, time_limit)
+            .is_some_and(|results| runner::print_summary(&results));
+
+        if passed && submit_on_pass {
+            println!();
+            submitter::submit(
+                path.into(),
+                submitter::SubmitOptions {
+                    bundle: sources.len() > 1,
+                    include_dirs: vec![],
+                    libraries: vec![],
+                    force: false,
+                    skip_checks: false,
+                    queue: false,
+                    problem: None,
+                },
+            );
+            return;
+        }
+        println!();
+        println!(
+            "{}",
+            "Waiting for changes... (Ctrl+C to stop)".bright_black()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn watches_included_headers() {
+        let dir = TestDir::new("watch");
+        dir.write("dsu.h", "#pragma once\nstruct Dsu {};\n");
+        let cpp = dir.write("main.cpp", "#include \"dsu.h\"\nint main() {}\n");
+        let python = dir.write("main.py", "print(1)\n");
+
+        assert_eq!(sources(&cpp).len(), 2);
+        assert_eq!(sources(&python), vec![python]);
+    }
+}